@@ -0,0 +1,131 @@
+//! Scoring of existing addresses by how rare their most striking features are, to compare a found
+//! address against another run's or decide whether to keep hunting.
+//!
+//! Each feature's rarity is the chance that a random address has it at least as strongly: a run of
+//! repeated characters at the start or end, the longest run anywhere, and a word from the chosen
+//! wordlists at the start or end. An address is as rare as its rarest feature.
+
+use crate::{constraints::ADDRESS_LENGTH_NIBBLES, wordlists::Word};
+use anyhow::{bail, Result};
+
+/// Feature of an address, with the chance of a random address having it.
+struct Feature {
+    description: String,
+    probability: f64,
+}
+
+/// Print the features of each address with their rarity, and the rarest feature overall, looking
+/// for the given words.
+pub fn run(addresses: &[String], words: &[Word]) -> Result<()> {
+    for (i, address) in addresses.iter().enumerate() {
+        let hex = address.strip_prefix("0x").unwrap_or(address).to_lowercase();
+        if hex.is_empty()
+            || hex.len() > ADDRESS_LENGTH_NIBBLES
+            || !hex.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            bail!("'{}' is not a valid address", address);
+        }
+        let hex = format!("{:0>width$}", hex, width = ADDRESS_LENGTH_NIBBLES);
+        if i > 0 {
+            println!();
+        }
+        println!("Address: 0x{}", hex);
+        let features = features(&hex, words);
+        if features.is_empty() {
+            println!("No notable features");
+            continue;
+        }
+        println!("{:<40} {:>24}", "Feature", "Rarity (1 in)");
+        for feature in &features {
+            let rarity = 1.0 / feature.probability;
+            let rarity = if rarity < 1e15 {
+                format!("{:.0}", rarity)
+            } else {
+                format!("{:.3e}", rarity)
+            };
+            println!("{:<40} {:>24}", feature.description, rarity);
+        }
+        let rarest = features
+            .iter()
+            .min_by(|a, b| a.probability.total_cmp(&b.probability))
+            .unwrap();
+        println!(
+            "Rarest feature: {}, about as rare as {:.1} fixed characters",
+            rarest.description,
+            -rarest.probability.log(16.0)
+        );
+    }
+    Ok(())
+}
+
+/// Notable features of a full-length address, as lowercase hex without a leading `0x`.
+fn features(hex: &str, words: &[Word]) -> Vec<Feature> {
+    let chars: Vec<char> = hex.chars().collect();
+    let mut features = vec![];
+
+    // A run of k repeated characters fixes k - 1 characters after the first.
+    let leading = chars.iter().take_while(|&&c| c == chars[0]).count();
+    if leading > 1 {
+        features.push(Feature {
+            description: format!("starts with {} '{}'s", leading, chars[0]),
+            probability: 16f64.powi(1 - leading as i32),
+        });
+    }
+    let last = chars[chars.len() - 1];
+    let trailing = chars.iter().rev().take_while(|&&c| c == last).count();
+    if trailing > 1 {
+        features.push(Feature {
+            description: format!("ends with {} '{}'s", trailing, last),
+            probability: 16f64.powi(1 - trailing as i32),
+        });
+    }
+    let (longest, longest_char) = longest_run(&chars);
+    if longest > leading.max(trailing) && longest > 2 {
+        // Bounded by the chance of a run starting at any of the positions it fits at.
+        let positions = (ADDRESS_LENGTH_NIBBLES - longest + 1) as f64;
+        features.push(Feature {
+            description: format!("has {} '{}'s in a row", longest, longest_char),
+            probability: (positions * 16f64.powi(1 - longest as i32)).min(1.0),
+        });
+    }
+
+    if let Some(word) = words.iter().find(|word| hex.starts_with(&word.spelling)) {
+        features.push(Feature {
+            description: format!("starts with the word {}", describe(word)),
+            probability: 16f64.powi(-(word.spelling.len() as i32)),
+        });
+    }
+    if let Some(word) = words.iter().find(|word| hex.ends_with(&word.spelling)) {
+        features.push(Feature {
+            description: format!("ends with the word {}", describe(word)),
+            probability: 16f64.powi(-(word.spelling.len() as i32)),
+        });
+    }
+    features
+}
+
+/// Describe a word, with its hex spelling if it differs.
+fn describe(word: &Word) -> String {
+    if word.word == word.spelling {
+        format!("'{}'", word.word)
+    } else {
+        format!("'{}' ({})", word.spelling, word.word)
+    }
+}
+
+/// Length and character of the longest run of a repeated character.
+fn longest_run(chars: &[char]) -> (usize, char) {
+    let (mut longest, mut longest_char) = (0, chars[0]);
+    let mut run = 0;
+    for (i, &c) in chars.iter().enumerate() {
+        run = if i > 0 && chars[i - 1] == c {
+            run + 1
+        } else {
+            1
+        };
+        if run > longest {
+            (longest, longest_char) = (run, c);
+        }
+    }
+    (longest, longest_char)
+}
@@ -0,0 +1,160 @@
+//! Persistence of cumulative attempt counts per search pattern, across runs.
+
+use crate::CliArgs;
+use anyhow::{Context, Result};
+use optivanity::{ED25519_SCHEME, MULTISIG_DOMAIN_SEPARATOR};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Serializes saves to attempts files within this process, such as by concurrently run jobs.
+static SAVES: Mutex<()> = Mutex::new(());
+
+/// Attempt counts file, as a tab-separated line of pattern key and cumulative attempts for each
+/// pattern that has been searched against.
+pub struct AttemptsFile {
+    path: PathBuf,
+    key: String,
+    prior: u64,
+    /// Attempts of this run already added to the file
+    saved: Mutex<u64>,
+}
+
+impl AttemptsFile {
+    /// Load prior attempts for a pattern key, treating a missing file as no prior attempts.
+    pub fn load(path: &Path, key: String) -> Result<Self> {
+        let prior = read_counts(path)?.get(&key).copied().unwrap_or(0);
+        Ok(Self {
+            path: path.to_path_buf(),
+            key,
+            prior,
+            saved: Mutex::new(0),
+        })
+    }
+
+    /// Attempts made against the pattern before this run.
+    pub fn prior(&self) -> u64 {
+        self.prior
+    }
+
+    /// Add the attempts made during this run since it last saved to the pattern's count, leaving
+    /// other patterns untouched.
+    ///
+    /// Counts are re-read and merged while holding a lock, so that runs saving to the same file at
+    /// the same time, such as concurrent jobs, each add their own attempts without losing others'.
+    pub fn save(&self, attempts_this_run: u64) -> Result<()> {
+        let _save = SAVES.lock().unwrap();
+        let _lock = lock(&self.path)?;
+        let mut saved = self.saved.lock().unwrap();
+        let mut counts = read_counts(&self.path)?;
+        *counts.entry(self.key.clone()).or_default() += attempts_this_run.saturating_sub(*saved);
+        let contents: String = counts
+            .iter()
+            .map(|(key, attempts)| format!("{}\t{}\n", key, attempts))
+            .collect();
+        // Write to a temporary file then rename, so an interrupted write can't lose prior counts.
+        let tmp_path = with_suffix(&self.path, ".tmp");
+        fs::write(&tmp_path, contents)
+            .with_context(|| format!("writing attempts file {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("writing attempts file {}", self.path.display()))?;
+        *saved = attempts_this_run;
+        Ok(())
+    }
+}
+
+/// Identify a pattern by search mode, prefix, and suffix.
+pub fn pattern_key(multisig: bool, prefix: Option<&str>, suffix: Option<&str>) -> String {
+    format!(
+        "{}:{}:{}",
+        if multisig { "multisig" } else { "standard" },
+        prefix.unwrap_or_default(),
+        suffix.unwrap_or_default()
+    )
+}
+
+/// Lock an attempts file against saves from other processes until the returned file is dropped.
+///
+/// Saves replace the attempts file, so the lock is held on a file beside it that is never replaced.
+fn lock(path: &Path) -> Result<File> {
+    let lock_path = with_suffix(path, ".lock");
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("opening attempts lock file {}", lock_path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+        // SAFETY: the descriptor is open for as long as `file` is.
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(std::io::Error::last_os_error())
+                .with_context(|| format!("locking attempts file {}", path.display()));
+        }
+    }
+    Ok(file)
+}
+
+/// Path with a suffix appended to its file name, rather than replacing any extension, so that it
+/// never names the file itself or another file beside it.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Identify a search by everything that changes its candidates: its pattern key, the creator
+/// patterns and domain separator of multisig searches, and the scheme byte, so that searches of
+/// different difficulty or derivation never share attempts. Settings left at their defaults are
+/// left out, so a plain search is identified by its pattern key alone.
+pub fn search_key(args: &CliArgs) -> String {
+    let mut key = pattern_key(
+        args.multisig,
+        args.prefix.as_deref(),
+        args.suffix.as_deref(),
+    );
+    if args.multisig {
+        if args.creator_prefix.is_some() || args.creator_suffix.is_some() {
+            key.push_str(&format!(
+                ";creator={}:{}",
+                args.creator_prefix.as_deref().unwrap_or_default(),
+                args.creator_suffix.as_deref().unwrap_or_default()
+            ));
+        }
+        // Domain separators are arbitrary text, so hex-encode them to keep keys to one field.
+        if args.multisig_domain != MULTISIG_DOMAIN_SEPARATOR {
+            key.push_str(&format!(";domain={}", hex::encode(&args.multisig_domain)));
+        }
+    }
+    if args.scheme_byte != ED25519_SCHEME {
+        key.push_str(&format!(";scheme={}", args.scheme_byte));
+    }
+    key
+}
+
+/// Read all pattern keys and their attempt counts.
+fn read_counts(path: &Path) -> Result<BTreeMap<String, u64>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("reading attempts file {}", path.display()))?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (key, attempts) = line
+                .split_once('\t')
+                .with_context(|| format!("malformed attempts file line '{}'", line))?;
+            let attempts = attempts
+                .trim()
+                .parse()
+                .with_context(|| format!("malformed attempt count in line '{}'", line))?;
+            Ok((key.to_string(), attempts))
+        })
+        .collect()
+}
@@ -0,0 +1,112 @@
+//! Append-only, hash-chained audit log of emitted addresses.
+//!
+//! Each line records when an address was emitted, by which run, for which pattern, and a
+//! fingerprint of its public key (never the private key), as tab-separated `key=value` fields:
+//! `prev`, `timestamp` (unix seconds), `run` (a UUID), `label`, `pattern`, `address`,
+//! `fingerprint`, and `hash`.
+//!
+//! `hash` is the SHA3-256 of the line up to (excluding) the tab before `hash=`, and `prev` is the
+//! hash of the previous line, or all zeros for the first. Editing, removing, or reordering any line
+//! therefore breaks the chain from that point on.
+
+use anyhow::{bail, Context, Result};
+use sha3::{Digest, Sha3_256};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Previous hash recorded on the first line of a log.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Separator between a record and its hash.
+const HASH_SEPARATOR: &str = "\thash=";
+
+/// Audit log open for appending.
+pub struct AuditLog {
+    path: PathBuf,
+    file: File,
+    last_hash: String,
+}
+
+impl AuditLog {
+    /// Open an audit log, creating it if needed, after verifying the existing chain.
+    pub fn open(path: &Path) -> Result<Self> {
+        let last_hash = if path.exists() {
+            verify(path)?.unwrap_or_else(|| GENESIS_HASH.to_string())
+        } else {
+            GENESIS_HASH.to_string()
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("opening audit log {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            last_hash,
+        })
+    }
+
+    /// Append a record for an emitted address, flushing it to disk before returning.
+    ///
+    /// # Arguments
+    ///
+    /// * `run` - Identifier of the run that emitted the address
+    /// * `label` - Label of the run that emitted the address, if any
+    /// * `pattern` - Pattern the address was searched for with
+    /// * `address` - Emitted address, as hex without a leading `0x`
+    /// * `public_key` - Public key controlling the address, as hex
+    pub fn append(
+        &mut self,
+        run: &str,
+        label: &str,
+        pattern: &str,
+        address: &str,
+        public_key: &str,
+    ) -> Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let fingerprint = hex::encode(Sha3_256::digest(hex::decode(public_key)?));
+        let record = format!(
+            "prev={}\ttimestamp={}\trun={}\tlabel={}\tpattern={}\taddress=0x{}\tfingerprint={}",
+            self.last_hash, timestamp, run, label, pattern, address, fingerprint
+        );
+        let hash = hex::encode(Sha3_256::digest(&record));
+        writeln!(self.file, "{}{}{}", record, HASH_SEPARATOR, hash)
+            .and_then(|_| self.file.sync_data())
+            .with_context(|| format!("writing audit log {}", self.path.display()))?;
+        self.last_hash = hash;
+        Ok(())
+    }
+}
+
+/// Verify the hash chain of an audit log, returning the hash of its last line, if any.
+pub fn verify(path: &Path) -> Result<Option<String>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("reading audit log {}", path.display()))?;
+    let mut last_hash: Option<String> = None;
+    for (i, line) in contents.lines().enumerate() {
+        let line_number = i + 1;
+        let Some((record, hash)) = line.rsplit_once(HASH_SEPARATOR) else {
+            bail!("audit log line {} has no hash", line_number);
+        };
+        let expected_prev = last_hash.as_deref().unwrap_or(GENESIS_HASH);
+        if record.split('\t').next() != Some(&format!("prev={}", expected_prev)) {
+            bail!(
+                "audit log line {} does not chain from the line before it",
+                line_number
+            );
+        }
+        if hex::encode(Sha3_256::digest(record)) != hash {
+            bail!("audit log line {} does not match its hash", line_number);
+        }
+        last_hash = Some(hash.to_string());
+    }
+    Ok(last_hash)
+}
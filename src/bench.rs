@@ -0,0 +1,133 @@
+//! Benchmarking address generation throughput, optionally guarded against a stored baseline so
+//! that performance work on key generation and hashing can't silently regress it.
+//!
+//! Each derivation mode is measured separately, along with its cost relative to standard addresses,
+//! since the extra hash of a multisig address makes it slower to search for.
+//!
+//! Baselines are small JSON files, for example
+//! `{"threads":8,"standard":412345.0,"multisig":398765.0}`, with rates in addresses per second.
+
+use crate::{estimate::sample_throughput, warnings};
+use anyhow::{anyhow, bail, Context, Result};
+use regex::Regex;
+use std::{fs, path::Path, thread, time::Duration};
+
+/// Address generation throughput measured by a benchmark.
+struct Throughput {
+    threads: usize,
+    standard: f64,
+    multisig: f64,
+}
+
+impl Throughput {
+    /// Measure standard and multisig throughput across threads, sampling each for a duration.
+    fn measure(threads: usize, duration: Duration) -> Self {
+        let measure = |multisig| -> f64 {
+            let handles: Vec<_> = (0..threads)
+                .map(|_| thread::spawn(move || sample_throughput(multisig, duration)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .sum()
+        };
+        Self {
+            threads,
+            standard: measure(false),
+            multisig: measure(true),
+        }
+    }
+
+    /// Serialize as a JSON baseline.
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"threads\":{},\"standard\":{:.1},\"multisig\":{:.1}}}\n",
+            self.threads, self.standard, self.multisig
+        )
+    }
+
+    /// Parse a JSON baseline written by [`Throughput::to_json`].
+    fn from_json(json: &str) -> Result<Self> {
+        let field = |name: &str| -> Result<f64> {
+            Regex::new(&format!(r#""{}"\s*:\s*([0-9.eE+-]+)"#, name))
+                .unwrap()
+                .captures(json)
+                .ok_or_else(|| anyhow!("baseline has no \"{}\" field", name))?[1]
+                .parse()
+                .with_context(|| format!("invalid \"{}\" in baseline", name))
+        };
+        Ok(Self {
+            threads: field("threads")? as usize,
+            standard: field("standard")?,
+            multisig: field("multisig")?,
+        })
+    }
+}
+
+/// Benchmark throughput, optionally saving it as a baseline, or failing if it has regressed by
+/// more than `max_regression` percent from a stored baseline.
+pub fn run(
+    threads: usize,
+    duration: Duration,
+    baseline: Option<&Path>,
+    save_baseline: Option<&Path>,
+    max_regression: f64,
+) -> Result<()> {
+    println!(
+        "Benchmarking across {} threads for {:?} per address type...",
+        threads, duration
+    );
+    let current = Throughput::measure(threads, duration);
+    println!("Standard: {:.0} addresses per second", current.standard);
+    println!(
+        "Multisig: {:.0} addresses per second, {:.2}x the cost of a standard address",
+        current.multisig,
+        current.standard / current.multisig
+    );
+
+    if let Some(path) = save_baseline {
+        fs::write(path, current.to_json())
+            .with_context(|| format!("writing baseline {}", path.display()))?;
+        println!("Saved baseline to {}", path.display());
+    }
+
+    let Some(path) = baseline else {
+        return Ok(());
+    };
+    let baseline = Throughput::from_json(
+        &fs::read_to_string(path)
+            .with_context(|| format!("reading baseline {}", path.display()))?,
+    )
+    .with_context(|| format!("parsing baseline {}", path.display()))?;
+    if baseline.threads != threads {
+        warnings::warn(
+            "baseline_threads_differ",
+            format!(
+                "baseline was measured across {} threads, not {}",
+                baseline.threads, threads
+            ),
+        );
+    }
+    let mut regressions = vec![];
+    for (name, current, baseline) in [
+        ("standard", current.standard, baseline.standard),
+        ("multisig", current.multisig, baseline.multisig),
+    ] {
+        let change = (current / baseline - 1.0) * 100.0;
+        println!(
+            "{}: {:+.1}% against baseline of {:.0} addresses per second",
+            name, change, baseline
+        );
+        if -change > max_regression {
+            regressions.push(name);
+        }
+    }
+    if !regressions.is_empty() {
+        bail!(
+            "{} throughput regressed by more than {}%",
+            regressions.join(" and "),
+            max_regression
+        );
+    }
+    Ok(())
+}
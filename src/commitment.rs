@@ -0,0 +1,103 @@
+//! Keyed commitments to private keys, for pay-on-delivery workflows where a seller proves they
+//! found a match before revealing its private key.
+//!
+//! The buyer supplies a secret key, and the seller publishes the HMAC-SHA3-256 of each private key
+//! under it alongside the address. Once paid, the seller reveals the private key, and the buyer
+//! checks it against the commitment, for example with
+//! `openssl dgst -sha3-256 -mac HMAC -macopt hexkey:<buyer key>` over the raw private key bytes.
+
+use anyhow::{bail, Context, Result};
+use sha3::{Digest, Sha3_256};
+use std::{fmt, str::FromStr};
+
+/// Block size of SHA3-256 in bytes, as used by HMAC.
+const BLOCK_SIZE: usize = 136;
+
+/// Buyer-supplied secret key to commit under, parsed from hex with or without a leading `0x`.
+#[derive(Clone)]
+pub struct CommitmentKey(Vec<u8>);
+
+impl FromStr for CommitmentKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let key =
+            hex::decode(s.strip_prefix("0x").unwrap_or(s)).context("commitment key is not hex")?;
+        if key.is_empty() {
+            bail!("commitment key is empty");
+        }
+        Ok(Self(key))
+    }
+}
+
+/// Keep the buyer's key out of debug output.
+impl fmt::Debug for CommitmentKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("CommitmentKey(..)")
+    }
+}
+
+impl CommitmentKey {
+    /// Commitment to a private key under this key.
+    pub fn commit(&self, private_key: &[u8]) -> [u8; 32] {
+        hmac_sha3_256(&self.0, private_key)
+    }
+}
+
+/// HMAC-SHA3-256 of a message under a key.
+fn hmac_sha3_256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha3_256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+    let inner = Sha3_256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha3_256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Key of `0x00`, `0x01`, ... as in NIST's HMAC-SHA3-256 examples.
+    fn counting_key(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn matches_nist_examples() {
+        assert_eq!(
+            hex::encode(hmac_sha3_256(
+                &counting_key(32),
+                b"Sample message for keylen<blocklen"
+            )),
+            "4fe8e202c4f058e8dddc23d8c34e467343e23555e24fc2f025d598f558f67205"
+        );
+        assert_eq!(
+            hex::encode(hmac_sha3_256(
+                &counting_key(168),
+                b"Sample message for keylen>blocklen"
+            )),
+            "9bcf2c238e235c3ce88404e813bd2f3a97185ac6f238c63d6229a00b07974258"
+        );
+    }
+
+    #[test]
+    fn keys_parse_from_hex_with_or_without_a_prefix() {
+        let key: CommitmentKey = "0x0001ff".parse().unwrap();
+        assert_eq!(key.0, [0x00, 0x01, 0xff]);
+        assert_eq!("0001ff".parse::<CommitmentKey>().unwrap().0, key.0);
+        for key in ["", "0x", "abc", "zz"] {
+            assert!(key.parse::<CommitmentKey>().is_err(), "{}", key);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Satisfiability pre-check of the constraints a search places on address characters, so that an
+//! impossible search is refused up front instead of running forever, and the exact difficulty of
+//! constraints that are satisfiable.
+
+use anyhow::{bail, Result};
+
+/// Number of hex characters in an account address.
+pub const ADDRESS_LENGTH_NIBBLES: usize = 64;
+
+/// Number of leading characters that are all zero in reserved addresses `0x0` through `0xf`.
+const RESERVED_ZERO_NIBBLES: usize = ADDRESS_LENGTH_NIBBLES - 1;
+
+/// A run of literal characters required at a fixed position in an address.
+pub struct Constraint<'a> {
+    /// Name of the constraint, for error messages
+    pub name: &'a str,
+    /// Position of the first required character
+    pub start: usize,
+    /// Required lowercase hex characters
+    pub characters: &'a str,
+}
+
+impl<'a> Constraint<'a> {
+    /// Constraint that an address starts with a prefix.
+    pub fn prefix(prefix: &'a str) -> Self {
+        Self {
+            name: "prefix",
+            start: 0,
+            characters: prefix,
+        }
+    }
+
+    /// Constraint that an address ends with a suffix, which must fit in an address.
+    pub fn suffix(suffix: &'a str) -> Self {
+        Self {
+            name: "suffix",
+            start: ADDRESS_LENGTH_NIBBLES.saturating_sub(suffix.len()),
+            characters: suffix,
+        }
+    }
+}
+
+/// Number of distinct address characters fixed by a set of satisfiable constraints, so that a
+/// random address matches them with probability `16^-n`.
+///
+/// Where a prefix and suffix overlap, characters they both fix only count once, rather than
+/// overstating the difficulty by simply adding their lengths.
+pub fn n_fixed_characters(constraints: &[Constraint]) -> usize {
+    let mut fixed = [false; ADDRESS_LENGTH_NIBBLES];
+    for constraint in constraints {
+        let end = (constraint.start + constraint.characters.len()).min(ADDRESS_LENGTH_NIBBLES);
+        fixed[constraint.start.min(end)..end].fill(true);
+    }
+    fixed.iter().filter(|&&fixed| fixed).count()
+}
+
+/// Refuse a set of constraints if any doesn't fit in an address, two require different characters
+/// at the same position, or together they only admit reserved framework addresses.
+pub fn check(constraints: &[Constraint]) -> Result<()> {
+    let mut required: [Option<(char, &str)>; ADDRESS_LENGTH_NIBBLES] =
+        [None; ADDRESS_LENGTH_NIBBLES];
+    for constraint in constraints {
+        if constraint.start + constraint.characters.len() > ADDRESS_LENGTH_NIBBLES {
+            bail!(
+                "{} is {} characters long, but addresses only have {}",
+                constraint.name,
+                constraint.characters.len(),
+                ADDRESS_LENGTH_NIBBLES
+            );
+        }
+        for (offset, c) in constraint.characters.chars().enumerate() {
+            let position = constraint.start + offset;
+            match required[position] {
+                Some((other, other_name)) if other != c => bail!(
+                    "{} and {} conflict at character {}: {} wants '{}' but {} wants '{}'",
+                    other_name,
+                    constraint.name,
+                    position + 1,
+                    other_name,
+                    other,
+                    constraint.name,
+                    c
+                ),
+                _ => required[position] = Some((c, constraint.name)),
+            }
+        }
+    }
+    if required[..RESERVED_ZERO_NIBBLES]
+        .iter()
+        .all(|required| matches!(required, Some(('0', _))))
+    {
+        bail!(
+            "only reserved framework addresses 0x0 through 0xf match, and they can't be generated"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constraints_that_fit_and_agree_are_satisfiable() {
+        let full = "ab".repeat(ADDRESS_LENGTH_NIBBLES / 2);
+        assert!(check(&[Constraint::prefix(&full)]).is_ok());
+        assert!(check(&[Constraint::prefix("cafe"), Constraint::suffix("beef")]).is_ok());
+        let overlapping = format!("{}cafe", "1".repeat(ADDRESS_LENGTH_NIBBLES - 4));
+        assert!(check(&[Constraint::prefix(&overlapping), Constraint::suffix("afe")]).is_ok());
+    }
+
+    #[test]
+    fn constraints_longer_than_an_address_are_rejected() {
+        let too_long = "a".repeat(ADDRESS_LENGTH_NIBBLES + 1);
+        let error = check(&[Constraint::prefix(&too_long)]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "prefix is 65 characters long, but addresses only have 64"
+        );
+        assert!(check(&[Constraint::suffix(&too_long)]).is_err());
+    }
+
+    #[test]
+    fn overlapping_constraints_that_disagree_are_rejected() {
+        let prefix = "a".repeat(ADDRESS_LENGTH_NIBBLES - 1);
+        let error = check(&[Constraint::prefix(&prefix), Constraint::suffix("bb")]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "prefix and suffix conflict at character 63: prefix wants 'a' but suffix wants 'b'"
+        );
+    }
+
+    #[test]
+    fn constraints_that_only_reserved_addresses_meet_are_rejected() {
+        let zeros = "0".repeat(RESERVED_ZERO_NIBBLES);
+        assert!(check(&[Constraint::prefix(&zeros)]).is_err());
+        assert!(check(&[
+            Constraint::prefix(&zeros[..40]),
+            Constraint::suffix(&zeros[..23])
+        ])
+        .is_ok());
+        let split = [
+            Constraint::prefix(&zeros[..40]),
+            Constraint::suffix(&zeros[..24]),
+        ];
+        assert_eq!(
+            check(&split).unwrap_err().to_string(),
+            "only reserved framework addresses 0x0 through 0xf match, and they can't be generated"
+        );
+        let reserved_with_a_one = format!("{}1", zeros);
+        assert!(check(&[Constraint::prefix(&reserved_with_a_one)]).is_err());
+        let zeros_then_one = format!("{}1", &zeros[..RESERVED_ZERO_NIBBLES - 1]);
+        assert!(check(&[Constraint::prefix(&zeros_then_one)]).is_ok());
+    }
+
+    #[test]
+    fn characters_fixed_by_overlapping_constraints_count_once() {
+        assert_eq!(n_fixed_characters(&[]), 0);
+        let disjoint = [Constraint::prefix("cafe"), Constraint::suffix("beef")];
+        assert_eq!(n_fixed_characters(&disjoint), 8);
+        let prefix = "a".repeat(40);
+        let overlapping = [Constraint::prefix(&prefix), Constraint::suffix(&prefix)];
+        assert_eq!(n_fixed_characters(&overlapping), ADDRESS_LENGTH_NIBBLES);
+        let nested = [Constraint::prefix(&prefix), Constraint::prefix("aa")];
+        assert_eq!(n_fixed_characters(&nested), 40);
+    }
+}
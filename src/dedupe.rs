@@ -0,0 +1,130 @@
+//! Address book of emitted addresses, shared across runs so that none is emitted twice, for
+//! example when resuming a run or merging the output of several machines.
+//!
+//! The database is a text file with one `0x`-prefixed address per line, appended to as addresses
+//! are emitted. Databases from several machines can be merged by concatenating them, then
+//! compacted to drop the duplicates.
+
+use anyhow::{bail, Context, Result};
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Dedupe database open for appending.
+pub struct DedupeDb {
+    path: PathBuf,
+    file: File,
+    addresses: HashSet<String>,
+    /// Number of duplicate addresses encountered by this run
+    pub duplicates: u64,
+}
+
+impl DedupeDb {
+    /// Open a dedupe database, creating it if needed, loading the addresses already recorded.
+    pub fn open(path: &Path) -> Result<Self> {
+        let addresses = if path.exists() {
+            read_addresses(path)?.into_iter().collect()
+        } else {
+            HashSet::new()
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("opening dedupe database {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            addresses,
+            duplicates: 0,
+        })
+    }
+
+    /// Whether an address, as hex without a leading `0x`, has already been recorded, counting it
+    /// as a duplicate if so.
+    pub fn is_duplicate(&mut self, address: &str) -> bool {
+        let duplicate = self.addresses.contains(address);
+        if duplicate {
+            self.duplicates += 1;
+        }
+        duplicate
+    }
+
+    /// Claim an address about to be emitted, as hex without a leading `0x`, so that this run
+    /// skips it if found again. It is only written to disk once emitted, by a [`DedupeRecorder`].
+    pub fn claim(&mut self, address: &str) {
+        self.addresses.insert(address.to_string());
+    }
+
+    /// Recorder appending emitted addresses to this database.
+    pub fn recorder(&self) -> Result<DedupeRecorder> {
+        Ok(DedupeRecorder {
+            path: self.path.clone(),
+            file: self
+                .file
+                .try_clone()
+                .with_context(|| format!("opening dedupe database {}", self.path.display()))?,
+        })
+    }
+}
+
+/// Appends emitted addresses to a dedupe database, separately from the addresses a run checks
+/// against, so that an address is only recorded once it has actually been emitted.
+pub struct DedupeRecorder {
+    path: PathBuf,
+    file: File,
+}
+
+impl DedupeRecorder {
+    /// Record an emitted address, as hex without a leading `0x`, flushing it to disk before
+    /// returning.
+    pub fn record(&mut self, address: &str) -> Result<()> {
+        writeln!(self.file, "0x{}", address)
+            .and_then(|_| self.file.sync_data())
+            .with_context(|| format!("writing dedupe database {}", self.path.display()))
+    }
+}
+
+/// Rewrite a dedupe database with each address once, in sorted order, returning the number of
+/// lines before and addresses after.
+pub fn compact(path: &Path) -> Result<(usize, usize)> {
+    let addresses = read_addresses(path)?;
+    let n_lines = addresses.len();
+    let addresses: BTreeSet<String> = addresses.into_iter().collect();
+    let contents: String = addresses
+        .iter()
+        .map(|address| format!("0x{}\n", address))
+        .collect();
+    // Write to a temporary file then rename, so an interrupted write can't lose addresses. The
+    // suffix is appended rather than replacing any extension, so it never names the database.
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".compacting");
+    let tmp_path = PathBuf::from(tmp_path);
+    fs::write(&tmp_path, contents)
+        .with_context(|| format!("writing dedupe database {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("writing dedupe database {}", path.display()))?;
+    Ok((n_lines, addresses.len()))
+}
+
+/// Read every address line, as lowercase hex without a leading `0x`, skipping blank lines.
+fn read_addresses(path: &Path) -> Result<Vec<String>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("reading dedupe database {}", path.display()))?;
+    let mut addresses = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let address = line.strip_prefix("0x").unwrap_or(line).to_lowercase();
+        if !address.chars().all(|c| c.is_ascii_hexdigit()) {
+            bail!("dedupe database line {} is not a hex address", i + 1);
+        }
+        addresses.push(address);
+    }
+    Ok(addresses)
+}
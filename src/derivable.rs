@@ -0,0 +1,206 @@
+//! Vanity search over the abstract public keys of AIP-113 derivable accounts.
+//!
+//! A derivable account's address is the SHA3-256 of the BCS-encoded function info of its
+//! authentication function, then the BCS-encoded abstract public key, then the derivable account
+//! scheme byte. The authentication function interprets the abstract public key however it likes, so
+//! an application that controls some of its bytes can search over them: here, a fixed leading part
+//! followed by a salt that is counted up until the address matches.
+//!
+//! The salt space is searched exhaustively rather than sampled: each thread takes every
+//! `threads`-th salt of the range, so no salt is hashed twice, and progress is the exact share of
+//! the range searched. Splitting the space into ranges spreads a search across machines without
+//! overlap.
+//!
+//! See https://github.com/aptos-foundation/AIPs/blob/main/aips/aip-113.md
+
+use crate::{is_match, prefix_bytes, suffix_bytes, AUTH_KEY_LENGTH};
+use anyhow::{bail, Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use sha3::{Digest, Sha3_256};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering::Relaxed},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Scheme identifier for derivable account addresses in the Aptos codebase.
+const DERIVABLE_ABSTRACTION_DERIVED_SCHEME: u8 = 5;
+
+/// Largest salt, in bytes, so that the salt space fits in a counter.
+const MAX_SALT_BYTES: usize = 8;
+
+/// Number of salts each thread searches between progress updates.
+const PROGRESS_BATCH: u64 = 1 << 12;
+
+/// Search salts appended to a fixed abstract public key prefix for a derivable account address
+/// matching a vanity pattern.
+///
+/// # Arguments
+///
+/// * `function` - Authentication function, as `ADDRESS::MODULE::FUNCTION`
+/// * `key_prefix` - Fixed leading bytes of the abstract public key, as hex
+/// * `salt_bytes` - Number of salt bytes to search over, appended to the fixed leading bytes
+/// * `first_salt` - First salt of the range to search
+/// * `salt_count` - Number of salts to search, or every salt from the first if `None`
+/// * `prefix` - The vanity prefix to search against
+/// * `suffix` - The vanity suffix to search against
+/// * `threads` - Number of threads to search with
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    function: &str,
+    key_prefix: &str,
+    salt_bytes: usize,
+    first_salt: u64,
+    salt_count: Option<u64>,
+    prefix: Option<String>,
+    suffix: Option<String>,
+    threads: usize,
+) -> Result<()> {
+    let function_info = function_info_bytes(function)?;
+    let key_prefix = hex::decode(key_prefix.strip_prefix("0x").unwrap_or(key_prefix))
+        .with_context(|| format!("abstract public key prefix '{}' is not hex", key_prefix))?;
+    if salt_bytes == 0 || salt_bytes > MAX_SALT_BYTES {
+        bail!("salt must be between 1 and {} bytes", MAX_SALT_BYTES);
+    }
+    for pattern in [&prefix, &suffix].into_iter().flatten() {
+        if !pattern.chars().all(|c| c.is_ascii_hexdigit()) {
+            bail!("pattern '{}' is not valid hex", pattern);
+        }
+    }
+    let prefix = prefix_bytes(prefix.map(|s| s.to_lowercase()))?;
+    let suffix = suffix_bytes(suffix.map(|s| s.to_lowercase()))?;
+
+    // The end of the range is exclusive, or `None` for the end of an 8-byte salt space, which
+    // doesn't fit in a counter.
+    let n_salts = 1u64.checked_shl(8 * salt_bytes as u32);
+    if n_salts.is_some_and(|n_salts| first_salt >= n_salts) {
+        bail!(
+            "first salt {} is beyond the {}-byte salt space",
+            first_salt,
+            salt_bytes
+        );
+    }
+    let end = match salt_count {
+        Some(0) => bail!("salt count must be positive"),
+        Some(salt_count) => match first_salt.checked_add(salt_count) {
+            Some(end) if n_salts.is_none_or(|n_salts| end <= n_salts) => Some(end),
+            _ => bail!("salt range runs beyond the {}-byte salt space", salt_bytes),
+        },
+        None => n_salts,
+    };
+    let range_length = end.map_or(u64::MAX - first_salt, |end| end - first_salt);
+    println!(
+        "Searching salts {} to {} for a derivable account address of {}",
+        first_salt,
+        end.map_or("2^64".to_string(), |end| end.to_string()),
+        function
+    );
+
+    let bar = ProgressBar::new(range_length);
+    bar.set_style(
+        ProgressStyle::with_template("{spinner} {percent:>3}% [{bar:30}] ETA {eta} | {msg}")
+            .unwrap()
+            .progress_chars("=> "),
+    );
+
+    // Each thread counts through every `threads`-th salt of the range.
+    let found = Arc::new(AtomicBool::new(false));
+    let searched = Arc::new(AtomicU64::new(0));
+    let handles: Vec<_> = (0..threads as u64)
+        .map(|thread_index| {
+            let (found, searched) = (found.clone(), searched.clone());
+            let (prefix, suffix) = (prefix.clone(), suffix.clone());
+            let mut preimage = function_info.clone();
+            uleb128(key_prefix.len() + salt_bytes, &mut preimage);
+            let key_offset = preimage.len();
+            let salt_offset = key_offset + key_prefix.len();
+            preimage.extend(&key_prefix);
+            preimage.resize(salt_offset + salt_bytes, 0);
+            preimage.push(DERIVABLE_ABSTRACTION_DERIVED_SCHEME);
+            thread::spawn(move || {
+                let mut salt = first_salt.checked_add(thread_index);
+                let mut n_searched = 0;
+                while let Some(current) = salt.filter(|&salt| end.is_none_or(|end| salt < end)) {
+                    if found.load(Relaxed) {
+                        break;
+                    }
+                    preimage[salt_offset..salt_offset + salt_bytes]
+                        .copy_from_slice(&current.to_be_bytes()[MAX_SALT_BYTES - salt_bytes..]);
+                    let address = Sha3_256::digest(&preimage).to_vec();
+                    if is_match(&address, &prefix, &suffix) && !found.swap(true, Relaxed) {
+                        let key = preimage[key_offset..preimage.len() - 1].to_vec();
+                        return Some((key, address));
+                    }
+                    n_searched += 1;
+                    if n_searched == PROGRESS_BATCH {
+                        searched.fetch_add(n_searched, Relaxed);
+                        n_searched = 0;
+                    }
+                    salt = current.checked_add(threads as u64);
+                }
+                searched.fetch_add(n_searched, Relaxed);
+                None
+            })
+        })
+        .collect();
+    while !handles.iter().all(|handle| handle.is_finished()) {
+        bar.set_position(searched.load(Relaxed));
+        thread::sleep(Duration::from_millis(100));
+    }
+    bar.finish_and_clear();
+    let mut winner = None;
+    for handle in handles {
+        winner = winner.or(handle.join().unwrap());
+    }
+
+    let Some((abstract_public_key, address)) = winner else {
+        bail!("no salt in the range gives an address matching the pattern");
+    };
+    println!("Derivable account address: 0x{}", hex::encode(address));
+    println!("Authentication function:   {}", function);
+    println!(
+        "Abstract public key:       0x{}",
+        hex::encode(abstract_public_key)
+    );
+    Ok(())
+}
+
+/// BCS-encode the function info of a function given as `ADDRESS::MODULE::FUNCTION`.
+fn function_info_bytes(function: &str) -> Result<Vec<u8>> {
+    let parts: Vec<&str> = function.split("::").collect();
+    let [address, module_name, function_name] = parts[..] else {
+        bail!("function '{}' is not ADDRESS::MODULE::FUNCTION", function);
+    };
+    let address = address.strip_prefix("0x").unwrap_or(address);
+    if address.is_empty()
+        || address.len() > 2 * AUTH_KEY_LENGTH
+        || !address.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        bail!(
+            "function '{}' does not start with a valid address",
+            function
+        );
+    }
+    let mut bytes =
+        hex::decode(format!("{:0>width$}", address, width = 2 * AUTH_KEY_LENGTH)).unwrap();
+    for name in [module_name, function_name] {
+        if name.is_empty() {
+            bail!("function '{}' has an empty name", function);
+        }
+        uleb128(name.len(), &mut bytes);
+        bytes.extend(name.as_bytes());
+    }
+    Ok(bytes)
+}
+
+/// Append a length as a BCS ULEB128 integer.
+fn uleb128(mut n: usize, bytes: &mut Vec<u8>) {
+    while n >= 0x80 {
+        bytes.push((n as u8 & 0x7f) | 0x80);
+        n >>= 7;
+    }
+    bytes.push(n as u8);
+}
@@ -0,0 +1,722 @@
+//! Searches spread across a fleet of machines: a coordinator hands each connecting worker the same
+//! search, aggregates their progress and matches, and stops them all once enough are found.
+//!
+//! The protocol is line-based over TCP, with tab-separated fields:
+//!
+//! * Handshake, proving to each side that the other knows the fleet's shared token without
+//!   sending it: the worker sends `hello` and a random nonce, the coordinator answers `hello`, a
+//!   nonce of its own, and its proof, and the worker replies `auth` and its proof. Each proof is
+//!   the SHA3-256 hash of the token, the prover's role, and both nonces.
+//! * Coordinator to worker: `search` followed by the options defining the search, once
+//!   authenticated, then `stop` once the search is over.
+//! * Worker to coordinator: `progress`, attempts, and smoothed rate, every second, and `match`,
+//!   address, public key, and the worker's scheme byte and hex-encoded multisig domain separator,
+//!   for each match. The coordinator re-derives each match's address from its public key, and
+//!   only counts it if the address matches and fits the search's patterns, so that a buggy or
+//!   stale worker can't end the search with bogus matches.
+//!
+//! The coordinator only decides what to search for: workers accept nothing but the
+//! [`SEARCH_OPTIONS`] from it, and take every option about where matches are written, what they
+//! connect to, and how addresses are derived from their own command line.
+//!
+//! Private keys never leave the workers: each outputs its matches locally, as a single search
+//! would, and the coordinator only learns their addresses and public keys. Nothing is encrypted,
+//! and messages after the handshake aren't authenticated, so run fleets on a private network.
+//! Peers that never authenticate cost the coordinator little: handshakes must finish within
+//! [`HANDSHAKE_TIMEOUT`], lines are limited to [`MAX_LINE`] bytes, and at most
+//! [`MAX_CONNECTIONS`] connections are handled at once.
+
+use crate::{search, validate_args, warnings, CliArgs};
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use indicatif::{ProgressBar, ProgressStyle};
+use optivanity::{
+    is_match, prefix_bytes, public_key_auth_key, suffix_bytes, MultisigPreimage,
+    SEQUENCE_NUMBER_MULTISIG,
+};
+use sha3::{Digest, Sha3_256};
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long the coordinator waits for stopped workers to report their final attempts.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long either side waits for the other's half of the handshake, in total however slowly it
+/// arrives.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest line either side reads, in bytes, so that a peer can't exhaust memory by never ending
+/// one.
+const MAX_LINE: u64 = 4096;
+
+/// Most connections the coordinator handles at once, authenticated or not, so that a flood of
+/// connections can't exhaust its threads.
+const MAX_CONNECTIONS: usize = 256;
+
+/// Options a coordinator may set on its workers, each with whether it takes a value: those that
+/// define what is searched for, and nothing that writes files, connects anywhere, or changes how
+/// addresses are derived. `--partition` is left to each worker, since handing every worker the
+/// same partition would have them all search the same addresses.
+const SEARCH_OPTIONS: [(&str, bool); 6] = [
+    ("--prefix", true),
+    ("--suffix", true),
+    ("--multisig", false),
+    ("--count", true),
+    ("--creator-prefix", true),
+    ("--creator-suffix", true),
+];
+
+/// Short forms of search options.
+const SHORT_SEARCH_OPTIONS: [(&str, &str); 4] = [
+    ("-p", "--prefix"),
+    ("-s", "--suffix"),
+    ("-m", "--multisig"),
+    ("-c", "--count"),
+];
+
+/// Worker connected to a coordinator, with what it last reported.
+struct Worker {
+    peer: String,
+    stream: TcpStream,
+    connected: bool,
+    attempts: u64,
+    rate: f64,
+}
+
+/// Workers and matches of a coordinated search.
+#[derive(Default)]
+struct Fleet {
+    workers: Vec<Worker>,
+    found: u64,
+}
+
+/// Coordinate a search across the workers that connect to a listening address, until the
+/// search's count of matches has been found.
+///
+/// # Arguments
+///
+/// * `listen` - Address to listen for workers on, as `host:port`
+/// * `token` - Shared token workers must prove they know
+/// * `search_argv` - Options defining the search for every worker to run, among
+///   [`SEARCH_OPTIONS`]
+pub fn serve(listen: &str, token: &str, search_argv: &[String]) -> Result<()> {
+    let search_options = search_options(search_argv)?;
+    let args = validate_args(CliArgs::try_parse_from(
+        std::iter::once("optivanity".to_string()).chain(search_options.iter().cloned()),
+    )?)?;
+
+    let listener = TcpListener::bind(listen).with_context(|| format!("listening on {}", listen))?;
+    listener.set_nonblocking(true)?;
+    println!(
+        "Coordinating a search for {} matches, waiting for workers on {}",
+        args.count,
+        listener.local_addr()?
+    );
+    println!();
+
+    let start_time = Instant::now();
+    let bar = ProgressBar::new_spinner();
+    bar.set_style(ProgressStyle::with_template("{spinner} {msg}").unwrap());
+    let fleet = Arc::new(Mutex::new(Fleet::default()));
+    let connections = Arc::new(AtomicUsize::new(0));
+    let search_line = format!("search\t{}\n", search_options.join("\t"));
+    while fleet.lock().unwrap().found < args.count {
+        match listener.accept() {
+            Ok((_, peer)) if connections.load(Relaxed) >= MAX_CONNECTIONS => {
+                bar.suspend(|| {
+                    warnings::warn(
+                        "too_many_connections",
+                        format!(
+                            "refused {}, already handling {} connections",
+                            peer, MAX_CONNECTIONS
+                        ),
+                    )
+                });
+            }
+            Ok((stream, peer)) => {
+                let peer = peer.to_string();
+                connections.fetch_add(1, Relaxed);
+                let (fleet, bar, connections) = (fleet.clone(), bar.clone(), connections.clone());
+                let (args, search_line) = (args.clone(), search_line.clone());
+                let token = token.to_string();
+                thread::spawn(move || {
+                    if let Err(error) =
+                        handle_worker(stream, &peer, &token, &search_line, &args, &fleet, &bar)
+                    {
+                        bar.suspend(|| {
+                            warnings::warn(
+                                "worker_disconnected",
+                                format!("worker {} disconnected: {:#}", peer, error),
+                            )
+                        });
+                    }
+                    connections.fetch_sub(1, Relaxed);
+                });
+            }
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(100))
+            }
+            Err(error) => return Err(error).context("accepting a worker"),
+        }
+        let fleet = fleet.lock().unwrap();
+        let connected = fleet.workers.iter().filter(|worker| worker.connected);
+        bar.set_message(format!(
+            "Workers: {} | Iterations per second: {:.0} it/s | {} of {} found",
+            connected.clone().count(),
+            connected.map(|worker| worker.rate).sum::<f64>(),
+            fleet.found,
+            args.count
+        ));
+        bar.tick();
+    }
+    bar.finish_and_clear();
+
+    // Stop every worker, then give them a moment to report their final attempts and hang up.
+    for worker in fleet
+        .lock()
+        .unwrap()
+        .workers
+        .iter()
+        .filter(|worker| worker.connected)
+    {
+        let _ = (&worker.stream).write_all(b"stop\n");
+    }
+    let stopped_at = Instant::now();
+    while stopped_at.elapsed() < SHUTDOWN_TIMEOUT
+        && fleet
+            .lock()
+            .unwrap()
+            .workers
+            .iter()
+            .any(|worker| worker.connected)
+    {
+        thread::sleep(Duration::from_millis(100));
+    }
+    let fleet = fleet.lock().unwrap();
+    println!("Elapsed time: {:#?}", start_time.elapsed());
+    println!("Workers: {}", fleet.workers.len());
+    println!(
+        "Total addresses generated: {}",
+        fleet
+            .workers
+            .iter()
+            .map(|worker| worker.attempts)
+            .sum::<u64>()
+    );
+    Ok(())
+}
+
+/// Authenticate a newly connected worker and hand it the search, then record what it reports until
+/// it disconnects.
+fn handle_worker(
+    mut stream: TcpStream,
+    peer: &str,
+    token: &str,
+    search_line: &str,
+    args: &CliArgs,
+    fleet: &Mutex<Fleet>,
+    bar: &ProgressBar,
+) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    let mut reader = BufReader::new(DeadlineReader::new(stream.try_clone()?));
+    let worker_nonce = match &read_fields(&mut reader)?[..] {
+        [hello, nonce] if hello == "hello" => nonce.clone(),
+        _ => bail!("expected a hello"),
+    };
+    let coordinator_nonce = nonce();
+    stream.write_all(
+        format!(
+            "hello\t{}\t{}\n",
+            coordinator_nonce,
+            proof(token, "coordinator", &worker_nonce, &coordinator_nonce)
+        )
+        .as_bytes(),
+    )?;
+    match &read_fields(&mut reader)?[..] {
+        [auth, worker_proof]
+            if auth == "auth"
+                && *worker_proof == proof(token, "worker", &worker_nonce, &coordinator_nonce) => {}
+        _ => bail!("wrong fleet token"),
+    }
+    reader.get_mut().clear_deadline()?;
+    stream.write_all(search_line.as_bytes())?;
+    let index = {
+        let mut fleet = fleet.lock().unwrap();
+        fleet.workers.push(Worker {
+            peer: peer.to_string(),
+            stream: stream.try_clone()?,
+            connected: true,
+            attempts: 0,
+            rate: 0.0,
+        });
+        fleet.workers.len() - 1
+    };
+    bar.suspend(|| println!("Worker {} joined", peer));
+
+    let result = std::iter::from_fn(|| read_line(&mut reader).transpose()).try_for_each(|line| {
+        let line = line?;
+        let fields: Vec<&str> = line.split('\t').collect();
+        let mut fleet = fleet.lock().unwrap();
+        match fields[..] {
+            ["progress", attempts, rate] => {
+                let worker = &mut fleet.workers[index];
+                worker.attempts = attempts.parse()?;
+                worker.rate = rate.parse()?;
+            }
+            ["match", address, public_key, scheme_byte, multisig_domain] => {
+                if let Err(error) =
+                    verify_match(args, address, public_key, scheme_byte, multisig_domain)
+                {
+                    bar.suspend(|| {
+                        warnings::warn(
+                            "invalid_match",
+                            format!("dropped a match from worker {}: {:#}", peer, error),
+                        )
+                    });
+                    return Ok(());
+                }
+                fleet.found += 1;
+                bar.suspend(|| {
+                    println!(
+                        "Match from worker {}: {}",
+                        fleet.workers[index].peer,
+                        args.address_format.format(address)
+                    );
+                    println!("Public key: 0x{}", public_key);
+                    println!();
+                });
+            }
+            _ => bail!("unexpected message '{}'", line),
+        }
+        Ok(())
+    });
+    fleet.lock().unwrap().workers[index].connected = false;
+    if result.is_ok() {
+        bar.suspend(|| println!("Worker {} left", peer));
+    }
+    result
+}
+
+/// Check that a match reported by a worker is genuine: that its address derives from its public key,
+/// under the scheme byte and multisig domain separator the worker derives with, and fits the
+/// search's patterns.
+fn verify_match(
+    args: &CliArgs,
+    address: &str,
+    public_key: &str,
+    scheme_byte: &str,
+    multisig_domain: &str,
+) -> Result<()> {
+    let public_key = hex::decode(public_key).context("public key isn't hex")?;
+    if public_key.len() != ed25519_dalek::PUBLIC_KEY_LENGTH {
+        bail!("public key is {} bytes long", public_key.len());
+    }
+    let auth_key = public_key_auth_key(&public_key, scheme_byte.parse()?);
+    let derived = if args.multisig {
+        let multisig_domain = String::from_utf8(hex::decode(multisig_domain)?)?;
+        MultisigPreimage::new(&multisig_domain, SEQUENCE_NUMBER_MULTISIG).account_address(&auth_key)
+    } else {
+        auth_key.clone()
+    };
+    if hex::encode(&derived) != address {
+        bail!("address {} doesn't derive from its public key", address);
+    }
+    if !is_match(
+        &derived,
+        &prefix_bytes(args.prefix.clone())?,
+        &suffix_bytes(args.suffix.clone())?,
+    ) {
+        bail!("address {} doesn't fit the search's patterns", address);
+    }
+    if args.multisig
+        && !is_match(
+            &auth_key,
+            &prefix_bytes(args.creator_prefix.clone())?,
+            &suffix_bytes(args.creator_suffix.clone())?,
+        )
+    {
+        bail!(
+            "creator of {} doesn't fit the search's creator patterns",
+            address
+        );
+    }
+    Ok(())
+}
+
+/// Connect to a coordinator and run the search it hands out, reporting progress and matches to
+/// it until it says the search is over.
+///
+/// # Arguments
+///
+/// * `connect` - Address of the coordinator, as `host:port`
+/// * `token` - Shared token the coordinator must prove it knows
+/// * `threads` - Number of threads to search with, in place of the search's own thread count
+/// * `local_argv` - This worker's own options, such as where to output matches, which the
+///   coordinator can't set
+pub fn work(
+    connect: &str,
+    token: &str,
+    threads: Option<usize>,
+    local_argv: &[String],
+) -> Result<()> {
+    let mut stream = TcpStream::connect(connect)
+        .with_context(|| format!("connecting to coordinator {}", connect))?;
+    let mut reader = BufReader::new(DeadlineReader::new(stream.try_clone()?));
+    let worker_nonce = nonce();
+    stream.write_all(format!("hello\t{}\n", worker_nonce).as_bytes())?;
+    let coordinator_nonce = match &read_fields(&mut reader)
+        .with_context(|| format!("authenticating coordinator {}", connect))?[..]
+    {
+        [hello, nonce, coordinator_proof]
+            if hello == "hello"
+                && *coordinator_proof == proof(token, "coordinator", &worker_nonce, nonce) =>
+        {
+            nonce.clone()
+        }
+        _ => bail!("coordinator {} doesn't know the fleet token", connect),
+    };
+    stream.write_all(
+        format!(
+            "auth\t{}\n",
+            proof(token, "worker", &worker_nonce, &coordinator_nonce)
+        )
+        .as_bytes(),
+    )?;
+    reader.get_mut().clear_deadline()?;
+    let fields = read_fields(&mut reader)
+        .with_context(|| format!("awaiting a search from coordinator {}", connect))?;
+    let Some((kind, search_argv)) = fields.split_first() else {
+        unreachable!("splitting a line always yields a field");
+    };
+    if kind != "search" {
+        bail!("coordinator {} didn't send a search", connect);
+    }
+    let search_argv = search_options(search_argv)
+        .with_context(|| format!("refusing the search from coordinator {}", connect))?;
+    let mut argv = vec!["optivanity".to_string()];
+    argv.extend(local_argv.iter().cloned());
+    argv.extend(search_argv);
+    if let Some(threads) = threads {
+        argv.extend(["--threads".to_string(), threads.to_string()]);
+    }
+    let mut args = CliArgs::try_parse_from(argv)?;
+    if args.offline {
+        bail!("--offline searches can't report to a coordinator over the network");
+    }
+    if args.shuffle_output {
+        bail!("--shuffle-output searches hold back the matches a coordinator counts");
+    }
+    if args.patterns.is_some() {
+        bail!("--patterns searches can't be coordinated");
+    }
+    if args.first_match_fast {
+        bail!("--first-match-fast searches don't report the progress a coordinator needs");
+    }
+
+    // Stop once the coordinator says so, or hangs up.
+    let link = CoordinatorLink {
+        stream: Arc::new(Mutex::new(stream)),
+        peer: connect.to_string(),
+        derivation: format!(
+            "{}\t{}",
+            args.scheme_byte,
+            hex::encode(&args.multisig_domain)
+        ),
+        stopped: Arc::new(AtomicBool::new(false)),
+    };
+    let stopped = link.stopped.clone();
+    thread::spawn(move || {
+        for line in std::iter::from_fn(|| read_line(&mut reader).transpose()) {
+            if line.map_or(true, |line| line == "stop") {
+                break;
+            }
+        }
+        stopped.store(true, Relaxed);
+    });
+    args.coordinator = Some(link.clone());
+    println!("Searching for coordinator {}", connect);
+    println!();
+    let summary = search(&validate_args(args)?)?;
+    // Report the final attempts, which the coordinator may already have stopped listening for.
+    let _ = link.report_progress(summary.attempts, 0.0);
+    Ok(())
+}
+
+/// Normalize options to long `--option value` form, refusing any but the [`SEARCH_OPTIONS`].
+fn search_options(argv: &[String]) -> Result<Vec<String>> {
+    let mut options = vec![];
+    let mut argv = argv.iter().filter(|arg| !arg.is_empty());
+    while let Some(arg) = argv.next() {
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let name = SHORT_SEARCH_OPTIONS
+            .iter()
+            .find(|(short, _)| *short == name)
+            .map_or(name, |(_, long)| long);
+        let Some(&(name, takes_value)) = SEARCH_OPTIONS.iter().find(|(long, _)| *long == name)
+        else {
+            bail!(
+                "'{}' doesn't define the search, only {} are shared with workers",
+                arg,
+                SEARCH_OPTIONS.map(|(name, _)| name).join(", ")
+            );
+        };
+        options.push(name.to_string());
+        if takes_value {
+            let Some(value) = inline_value.or_else(|| argv.next().cloned()) else {
+                bail!("{} needs a value", name);
+            };
+            if value.contains(['\t', '\n', '\r']) {
+                bail!("search options can't contain tabs or line breaks");
+            }
+            options.push(value);
+        } else if inline_value.is_some() {
+            bail!("{} doesn't take a value", name);
+        }
+    }
+    Ok(options)
+}
+
+/// Random nonce for one side of a handshake, as hex.
+fn nonce() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
+
+/// Proof that one side of a handshake, in the given role, knows the fleet token, as hex. Each part
+/// is length-prefixed so that no two different inputs hash alike.
+fn proof(token: &str, role: &str, worker_nonce: &str, coordinator_nonce: &str) -> String {
+    let mut hasher = Sha3_256::new();
+    for part in [token, role, worker_nonce, coordinator_nonce] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Read a line of tab-separated fields, failing if the connection closes first.
+fn read_fields(reader: &mut impl BufRead) -> Result<Vec<String>> {
+    let line = read_line(reader)?.context("connection closed")?;
+    Ok(line.split('\t').map(String::from).collect())
+}
+
+/// Read a line without its line break, or `None` once the connection closes, failing on lines
+/// longer than [`MAX_LINE`].
+fn read_line(reader: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = String::new();
+    let n_read = reader.take(MAX_LINE).read_line(&mut line)?;
+    if n_read == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') && n_read as u64 == MAX_LINE {
+        bail!("line longer than {} bytes", MAX_LINE);
+    }
+    Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+}
+
+/// Connection read with a deadline for the handshake: each read times out once the deadline has
+/// passed, however slowly the peer trickles in bytes, rather than only after a quiet spell.
+struct DeadlineReader {
+    stream: TcpStream,
+    deadline: Option<Instant>,
+}
+
+impl DeadlineReader {
+    /// Read from a connection, with [`HANDSHAKE_TIMEOUT`] from now to complete the handshake.
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            deadline: Some(Instant::now() + HANDSHAKE_TIMEOUT),
+        }
+    }
+
+    /// Lift the deadline once the handshake is done, so that reads wait indefinitely.
+    fn clear_deadline(&mut self) -> Result<()> {
+        self.deadline = None;
+        self.stream.set_read_timeout(None)?;
+        Ok(())
+    }
+}
+
+impl Read for DeadlineReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(deadline) = self.deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(io::Error::new(ErrorKind::TimedOut, "handshake timed out"));
+            }
+            self.stream.set_read_timeout(Some(remaining))?;
+            return self.stream.read(buf).map_err(|error| match error.kind() {
+                ErrorKind::WouldBlock | ErrorKind::TimedOut => {
+                    io::Error::new(ErrorKind::TimedOut, "handshake timed out")
+                }
+                _ => error,
+            });
+        }
+        self.stream.read(buf)
+    }
+}
+
+/// Connection from a worker to the coordinator of its search.
+#[derive(Clone)]
+pub struct CoordinatorLink {
+    stream: Arc<Mutex<TcpStream>>,
+    peer: String,
+    /// Scheme byte and hex-encoded multisig domain separator matches are derived with, as fields
+    derivation: String,
+    stopped: Arc<AtomicBool>,
+}
+
+impl fmt::Debug for CoordinatorLink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CoordinatorLink({})", self.peer)
+    }
+}
+
+impl CoordinatorLink {
+    /// Report attempts so far and the smoothed rate.
+    pub fn report_progress(&self, attempts: u64, rate: f64) -> Result<()> {
+        self.send(&format!("progress\t{}\t{:.0}\n", attempts, rate))
+    }
+
+    /// Report a match, given its address and public key as hex without a leading `0x`, along with
+    /// how it was derived so that the coordinator can check it.
+    pub fn report_match(&self, address: &str, public_key: &str) -> Result<()> {
+        self.send(&format!(
+            "match\t{}\t{}\t{}\n",
+            address, public_key, self.derivation
+        ))
+    }
+
+    /// Return `true` once the coordinator has said the search is over, or hung up.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Relaxed)
+    }
+
+    fn send(&self, line: &str) -> Result<()> {
+        self.stream
+            .lock()
+            .unwrap()
+            .write_all(line.as_bytes())
+            .with_context(|| format!("reporting to coordinator {}", self.peer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: [u8; 32] = [7; 32];
+
+    fn options(argv: &[&str]) -> Result<Vec<String>> {
+        search_options(&argv.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    fn args(argv: &[&str]) -> CliArgs {
+        CliArgs::try_parse_from(std::iter::once(&"optivanity").chain(argv)).unwrap()
+    }
+
+    #[test]
+    fn search_options_are_normalized_to_long_form() {
+        assert_eq!(
+            options(&["-p", "ca", "--suffix=fe", "-m", "", "-c", "3"]).unwrap(),
+            [
+                "--prefix",
+                "ca",
+                "--suffix",
+                "fe",
+                "--multisig",
+                "--count",
+                "3"
+            ]
+        );
+        assert_eq!(
+            options(&["--creator-prefix", "0", "--creator-suffix=1"]).unwrap(),
+            ["--creator-prefix", "0", "--creator-suffix", "1"]
+        );
+        assert!(options(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn options_that_dont_define_the_search_are_refused() {
+        for argv in [
+            &["--output", "matches.txt"][..],
+            &["--partition", "3/16"],
+            &["-o", "matches.txt"],
+            &["--threads=4"],
+            &["--multisig-domain", "x"],
+            &["ca"],
+            &["--prefix"],
+            &["--count=3", "--multisig=true"],
+            &["--prefix", "ca\tfe"],
+            &["--suffix=fe\n"],
+        ] {
+            assert!(options(argv).is_err(), "{:?}", argv);
+        }
+    }
+
+    #[test]
+    fn proofs_differ_by_role_and_nonce_order() {
+        let worker = proof("t0k", "worker", "aa", "bb");
+        assert_eq!(worker, proof("t0k", "worker", "aa", "bb"));
+        assert_ne!(worker, proof("t0k", "coordinator", "aa", "bb"));
+        assert_ne!(worker, proof("t0k", "worker", "bb", "aa"));
+        assert_ne!(worker, proof("t0kw", "orker", "aa", "bb"));
+        assert_ne!(worker, proof("t0l", "worker", "aa", "bb"));
+    }
+
+    #[test]
+    fn lines_longer_than_the_limit_are_refused() {
+        let mut reader = "progress\t10\t2.5\r\nmatch\n".as_bytes();
+        assert_eq!(read_fields(&mut reader).unwrap(), ["progress", "10", "2.5"]);
+        assert_eq!(read_line(&mut reader).unwrap().unwrap(), "match");
+        assert!(read_line(&mut reader).unwrap().is_none());
+        assert!(read_fields(&mut reader).is_err());
+
+        let longest = format!("{}\n", "a".repeat(MAX_LINE as usize - 1));
+        assert!(read_line(&mut longest.as_bytes()).unwrap().is_some());
+        let too_long = "a".repeat(MAX_LINE as usize + 1);
+        assert!(read_line(&mut too_long.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn only_genuine_matches_that_fit_the_search_are_counted() {
+        let public_key = hex::encode(PUBLIC_KEY);
+        let address = hex::encode(public_key_auth_key(&PUBLIC_KEY, 0));
+        let args = |pattern: &str| args(&["--prefix", pattern]);
+        let prefix = &address[..3];
+        assert!(verify_match(&args(prefix), &address, &public_key, "0", "").is_ok());
+        assert!(verify_match(&args("fff"), &address, &public_key, "0", "").is_err());
+        assert!(verify_match(&args(prefix), &address, &public_key, "1", "").is_err());
+        assert!(verify_match(&args(prefix), &address, &public_key[2..], "0", "").is_err());
+        let other_address = hex::encode(public_key_auth_key(&[8; 32], 0));
+        assert!(verify_match(&args(prefix), &other_address, &public_key, "0", "").is_err());
+    }
+
+    #[test]
+    fn multisig_matches_are_derived_with_the_workers_domain() {
+        let public_key = hex::encode(PUBLIC_KEY);
+        let auth_key = public_key_auth_key(&PUBLIC_KEY, 0);
+        let domain = "custom_domain";
+        let address = hex::encode(
+            MultisigPreimage::new(domain, SEQUENCE_NUMBER_MULTISIG).account_address(&auth_key),
+        );
+        let creator = hex::encode(&auth_key);
+        let other_creator = if creator.starts_with('0') { "1" } else { "0" };
+        let domain = hex::encode(domain);
+        let args = |creator_prefix| args(&["--multisig", "--creator-prefix", creator_prefix]);
+        assert!(verify_match(&args(&creator[..2]), &address, &public_key, "0", &domain).is_ok());
+        assert!(verify_match(&args(&creator[..2]), &address, &public_key, "0", "").is_err());
+        assert!(verify_match(&args(other_creator), &address, &public_key, "0", &domain).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! Report of what this build and machine can do, the first thing to ask for when debugging a
+//! performance report.
+
+use crate::{estimate, manifest, parallelism::Parallelism};
+use anyhow::Result;
+use rand::{rngs::OsRng, RngCore};
+use std::time::Duration;
+
+/// Number of random bytes to sample when checking the entropy source.
+const ENTROPY_SAMPLE_BYTES: usize = 4096;
+
+/// Largest tolerated deviation of the number of set bits in the entropy sample from half, in
+/// standard deviations.
+const MAX_BIT_BIAS_SIGMAS: f64 = 5.0;
+
+/// How long to sample single-thread throughput for.
+const THROUGHPUT_SAMPLE_DURATION: Duration = Duration::from_millis(500);
+
+/// Print the build, CPU features, parallelism, entropy source health, and throughput of this
+/// machine.
+pub fn run() -> Result<()> {
+    println!(
+        "Version:                  {} {}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    );
+    println!(
+        "Target:                   {}-{}{}",
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        if cfg!(debug_assertions) {
+            " (debug build, expect poor performance)"
+        } else {
+            ""
+        }
+    );
+    println!(
+        "Compiled features:        {}",
+        list(manifest::compiled_features())
+    );
+    println!(
+        "CPU features:             {}",
+        list(manifest::cpu_features())
+    );
+    println!("GPU devices:              none (this build only searches on the CPU)");
+    let parallelism = Parallelism::detect();
+    match &parallelism.available {
+        Ok(available) => println!("Available parallelism:    {} threads", available),
+        Err(error) => println!("Available parallelism:    unknown ({})", error),
+    }
+    match parallelism.quota {
+        Some(quota) => println!(
+            "CPU quota:                {:.2} CPUs (cgroup v{})",
+            quota.cpus, quota.cgroup_version
+        ),
+        None => println!("CPU quota:                none"),
+    }
+    println!("Default threads:          {}", parallelism.threads());
+    println!("Entropy source:           {}", check_entropy());
+    println!(
+        "Single-thread throughput: {:.0} standard, {:.0} multisig addresses per second",
+        estimate::sample_throughput(false, THROUGHPUT_SAMPLE_DURATION),
+        estimate::sample_throughput(true, THROUGHPUT_SAMPLE_DURATION)
+    );
+    Ok(())
+}
+
+/// Check that the operating system's random number generator works and isn't obviously biased.
+fn check_entropy() -> String {
+    let mut samples = [[0u8; ENTROPY_SAMPLE_BYTES]; 2];
+    for sample in samples.iter_mut() {
+        if let Err(error) = OsRng.try_fill_bytes(sample) {
+            return format!("FAILED, could not read random bytes ({})", error);
+        }
+    }
+    if samples[0] == samples[1] {
+        return "FAILED, returned the same bytes twice".to_string();
+    }
+    let n_bits = (ENTROPY_SAMPLE_BYTES * 8) as f64;
+    let n_ones: u32 = samples[0].iter().map(|byte| byte.count_ones()).sum();
+    let sigmas = (f64::from(n_ones) - n_bits / 2.0).abs() / (n_bits / 4.0).sqrt();
+    if sigmas > MAX_BIT_BIAS_SIGMAS {
+        return format!(
+            "FAILED, {} of {} sampled bits set, which is implausibly biased",
+            n_ones, n_bits
+        );
+    }
+    "ok (operating system random number generator)".to_string()
+}
+
+/// Format a list of names, or `none`.
+fn list(names: Vec<&str>) -> String {
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(", ")
+    }
+}
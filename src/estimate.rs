@@ -0,0 +1,128 @@
+//! Estimating how long searches would take on this machine, to compare candidate patterns before
+//! committing to one.
+
+use crate::{
+    auth_key_bytes_vec,
+    constraints::{self, Constraint},
+    forecast, MultisigPreimage, ED25519_SCHEME, MULTISIG_DOMAIN_SEPARATOR,
+    SEQUENCE_NUMBER_MULTISIG,
+};
+use anyhow::{bail, Context, Result};
+use ed25519_dalek::SigningKey;
+use num::BigInt;
+use std::time::{Duration, Instant};
+
+/// How long to sample single-thread throughput for.
+const THROUGHPUT_SAMPLE_DURATION: Duration = Duration::from_millis(500);
+
+/// Print a table comparing the difficulty, expected search time, 95th percentile search time, and
+/// optionally expected cost of several candidate patterns.
+///
+/// Each pattern is a prefix, or `PREFIX..SUFFIX` to also match a suffix. Only the number of address
+/// characters fixed affects difficulty, which is less than the total length when a long prefix and
+/// suffix overlap.
+pub fn run(
+    patterns: &[String],
+    multisig: bool,
+    count: u64,
+    threads: usize,
+    cost_per_hour: Option<f64>,
+) -> Result<()> {
+    let n_characters = patterns
+        .iter()
+        .map(|pattern| {
+            let lowercase = pattern.to_lowercase();
+            let (prefix, suffix) = lowercase.split_once("..").unwrap_or((&lowercase, ""));
+            if !(prefix.chars().chain(suffix.chars())).all(|c| c.is_ascii_hexdigit()) {
+                bail!("pattern '{}' is not valid hex", pattern);
+            }
+            let constraints = [Constraint::prefix(prefix), Constraint::suffix(suffix)];
+            constraints::check(&constraints)
+                .with_context(|| format!("pattern '{}' can't be matched", pattern))?;
+            Ok(constraints::n_fixed_characters(&constraints))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    println!("Measuring this machine's speed...");
+    let rate = single_thread_throughput(multisig) * threads as f64;
+    println!(
+        "Searching for {} {} address(es) at about {} addresses per second across {} threads:",
+        count,
+        if multisig { "multisig" } else { "standard" },
+        rate as u64,
+        threads
+    );
+    println!();
+
+    let mut header = format!(
+        "{:<20} {:>24} {:>16} {:>16}",
+        "Pattern", "Difficulty (1 in)", "Expected", "95% within"
+    );
+    if cost_per_hour.is_some() {
+        header.push_str(&format!(" {:>14}", "Expected cost"));
+    }
+    println!("{}", header);
+    for (pattern, n_characters) in patterns.iter().zip(n_characters) {
+        let expected = expected_seconds(n_characters, rate) * count as f64;
+        let p_match = 16f64.powi(-(n_characters as i32));
+        let within_95 = forecast::attempts_quantile(count, p_match, 0.95) / rate;
+        let mut row = format!(
+            "{:<20} {:>24} {:>16} {:>16}",
+            pattern,
+            BigInt::from(16).pow(n_characters as u32).to_string(),
+            format_seconds(expected),
+            format_seconds(within_95)
+        );
+        if let Some(cost_per_hour) = cost_per_hour {
+            row.push_str(&format!(" {:>14.2}", expected / 3600.0 * cost_per_hour));
+        }
+        println!("{}", row);
+    }
+    Ok(())
+}
+
+/// Measure how many addresses a single thread generates per second.
+pub fn single_thread_throughput(multisig: bool) -> f64 {
+    sample_throughput(multisig, THROUGHPUT_SAMPLE_DURATION)
+}
+
+/// Measure how many addresses the calling thread generates per second, over a given duration.
+pub fn sample_throughput(multisig: bool, duration: Duration) -> f64 {
+    let mut rng = rand::rngs::OsRng;
+    let mut multisig_preimage =
+        MultisigPreimage::new(MULTISIG_DOMAIN_SEPARATOR, SEQUENCE_NUMBER_MULTISIG);
+    let start_time = Instant::now();
+    let mut n_generated = 0;
+    while start_time.elapsed() < duration {
+        let auth_key = std::hint::black_box(auth_key_bytes_vec(
+            &SigningKey::generate(&mut rng),
+            ED25519_SCHEME,
+        ));
+        if multisig {
+            std::hint::black_box(multisig_preimage.account_address(&auth_key));
+        }
+        n_generated += 1;
+    }
+    n_generated as f64 / start_time.elapsed().as_secs_f64()
+}
+
+/// Expected number of seconds to find a single match for a pattern of a given character count.
+pub fn expected_seconds(n_characters: usize, rate: f64) -> f64 {
+    16f64.powi(n_characters as i32) / rate
+}
+
+/// Format a number of seconds in the largest sensible unit.
+pub fn format_seconds(seconds: f64) -> String {
+    const UNITS: [(&str, f64); 5] = [
+        ("years", 365.25 * 24.0 * 60.0 * 60.0),
+        ("days", 24.0 * 60.0 * 60.0),
+        ("hours", 60.0 * 60.0),
+        ("minutes", 60.0),
+        ("seconds", 1.0),
+    ];
+    let (unit, unit_seconds) = UNITS
+        .into_iter()
+        .find(|(_, unit_seconds)| seconds >= *unit_seconds)
+        .unwrap_or(UNITS[UNITS.len() - 1]);
+    format!("{:.1} {}", seconds / unit_seconds, unit)
+}
@@ -0,0 +1,218 @@
+//! Post-filters on matches, applied before output, so runs can thin their own results without
+//! external tooling.
+//!
+//! A filter compares a field of a match with a value, as `FIELD OP VALUE`:
+//!
+//! * Numeric fields `attempts` and `elapsed_ms` (since the previous match), and `total_attempts`
+//!   and `total_elapsed_ms` (since the run started), compare with a number using `<`, `<=`, `>`,
+//!   `>=`, `==`, or `!=`.
+//! * Text fields `address`, `auth_key`, and `public_key`, as `0x`-prefixed lowercase hex, compare
+//!   with a double-quoted string using `==` or `!=`, or match a double-quoted regular expression
+//!   using `=~` or `!~`.
+//!
+//! For example `attempts < 1000000`, or `address =~ "00$"`.
+
+use crate::sink::MatchRecord;
+use anyhow::{anyhow, bail, Context, Result};
+use regex::Regex;
+use std::str::FromStr;
+
+/// Filter on a field of a match.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    field: String,
+    condition: Condition,
+}
+
+/// Condition a field has to meet.
+#[derive(Clone, Debug)]
+enum Condition {
+    /// Compare a numeric field with a number
+    Number(Comparison, f64),
+    /// Compare a text field with a string, for equality or inequality
+    Text(bool, String),
+    /// Match a text field against a regular expression, or against its negation
+    Pattern(bool, Regex),
+}
+
+/// Comparison between a numeric field and a number.
+#[derive(Clone, Copy, Debug)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+/// Numeric fields that can be filtered on.
+const NUMERIC_FIELDS: [&str; 4] = [
+    "attempts",
+    "elapsed_ms",
+    "total_attempts",
+    "total_elapsed_ms",
+];
+
+/// Text fields that can be filtered on.
+const TEXT_FIELDS: [&str; 3] = ["address", "auth_key", "public_key"];
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let captures = Regex::new(r"^\s*(\w+)\s*(<=|>=|==|!=|=~|!~|<|>)\s*(.*?)\s*$")
+            .unwrap()
+            .captures(s)
+            .ok_or_else(|| anyhow!("expected FIELD OP VALUE, for example 'attempts < 1000000'"))?;
+        let (field, operator, value) = (&captures[1], &captures[2], &captures[3]);
+        let condition = if NUMERIC_FIELDS.contains(&field) {
+            let comparison = match operator {
+                "<" => Comparison::Less,
+                "<=" => Comparison::LessOrEqual,
+                ">" => Comparison::Greater,
+                ">=" => Comparison::GreaterOrEqual,
+                "==" => Comparison::Equal,
+                "!=" => Comparison::NotEqual,
+                _ => bail!(
+                    "'{}' can't be used with numeric field '{}'",
+                    operator,
+                    field
+                ),
+            };
+            let number = value
+                .parse()
+                .with_context(|| format!("'{}' is not a number", value))?;
+            Condition::Number(comparison, number)
+        } else if TEXT_FIELDS.contains(&field) {
+            let text = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .ok_or_else(|| anyhow!("'{}' is not a double-quoted string", value))?;
+            match operator {
+                "==" | "!=" => Condition::Text(operator == "==", text.to_string()),
+                "=~" | "!~" => Condition::Pattern(
+                    operator == "=~",
+                    Regex::new(text)
+                        .with_context(|| format!("'{}' is not a regular expression", text))?,
+                ),
+                _ => bail!("'{}' can't be used with text field '{}'", operator, field),
+            }
+        } else {
+            bail!(
+                "unknown field '{}', expected one of {}, {}",
+                field,
+                NUMERIC_FIELDS.join(", "),
+                TEXT_FIELDS.join(", ")
+            );
+        };
+        Ok(Self {
+            field: field.to_string(),
+            condition,
+        })
+    }
+}
+
+impl Filter {
+    /// Whether a match passes the filter.
+    pub fn passes(&self, record: &MatchRecord) -> bool {
+        match &self.condition {
+            Condition::Number(comparison, number) => {
+                let value = match self.field.as_str() {
+                    "attempts" => record.match_attempts as f64,
+                    "elapsed_ms" => record.match_elapsed.as_millis() as f64,
+                    "total_attempts" => record.attempts as f64,
+                    _ => record.elapsed.as_millis() as f64,
+                };
+                match comparison {
+                    Comparison::Less => value < *number,
+                    Comparison::LessOrEqual => value <= *number,
+                    Comparison::Greater => value > *number,
+                    Comparison::GreaterOrEqual => value >= *number,
+                    Comparison::Equal => value == *number,
+                    Comparison::NotEqual => value != *number,
+                }
+            }
+            Condition::Text(equal, text) => (self.text(record) == *text) == *equal,
+            Condition::Pattern(matching, pattern) => {
+                pattern.is_match(&self.text(record)) == *matching
+            }
+        }
+    }
+
+    /// Value of a text field, as `0x`-prefixed hex.
+    fn text(&self, record: &MatchRecord) -> String {
+        let hex = match self.field.as_str() {
+            "address" => record.address,
+            "auth_key" => record.auth_key,
+            _ => record.public_key,
+        };
+        format!("0x{}", hex)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn passes(filter: &str) -> bool {
+        let record = MatchRecord {
+            address: "cafe00",
+            auth_key: "beef01",
+            public_key: "1234",
+            private_key: None,
+            redacted: false,
+            commitment: None,
+            share_paths: &[],
+            signature: None,
+            multisig: false,
+            match_attempts: 1000,
+            match_elapsed: Duration::from_millis(250),
+            attempts: 5000,
+            elapsed: Duration::from_secs(2),
+            json: "",
+        };
+        filter.parse::<Filter>().unwrap().passes(&record)
+    }
+
+    #[test]
+    fn filters_compare_fields_with_values() {
+        for (filter, expected) in [
+            ("attempts < 1000", false),
+            ("attempts <= 1000", true),
+            ("attempts>999.5", true),
+            ("attempts >= 1e3", true),
+            ("attempts == 1000", true),
+            ("attempts != 1000", false),
+            ("elapsed_ms < 300", true),
+            ("total_attempts > 4999", true),
+            ("total_elapsed_ms == 2000", true),
+            (r#"address == "0xcafe00""#, true),
+            (r#"address != "0xcafe00""#, false),
+            (r#"auth_key =~ "01$""#, true),
+            (r#"public_key !~ "^0x12""#, false),
+            (r#"  address =~ "^0xcafe"  "#, true),
+        ] {
+            assert_eq!(passes(filter), expected, "{}", filter);
+        }
+    }
+
+    #[test]
+    fn malformed_filters_are_rejected() {
+        for filter in [
+            "",
+            "attempts",
+            "attempts 1000",
+            "nonce < 5",
+            "attempts =~ 5",
+            "attempts < many",
+            r#"attempts < "5""#,
+            "address == 0xcafe",
+            r#"address < "0xcafe""#,
+            r#"address =~ "(""#,
+        ] {
+            assert!(filter.parse::<Filter>().is_err(), "{}", filter);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Forecasting when a search is likely to complete.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Forecasts further out than this are reported as such, rather than as a date.
+const MAX_FORECAST: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+
+/// Number of further attempts after which there is a `probability` chance of having found
+/// `n_matches` more matches, when each attempt matches with chance `p_match`.
+///
+/// The number of matches in a given number of attempts is approximately Poisson distributed, so
+/// this solves for the expected match count `λ` at which `P(Poisson(λ) >= n_matches)` reaches
+/// `probability`, by bisection, then converts back to attempts.
+pub fn attempts_quantile(n_matches: u64, p_match: f64, probability: f64) -> f64 {
+    if n_matches == 0 {
+        return 0.0;
+    }
+    let k = n_matches as f64;
+    let (mut low, mut high) = (0.0, k + 20.0 * k.sqrt() + 20.0);
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if poisson_at_least(n_matches, mid) < probability {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    high / p_match
+}
+
+/// Chance of having found at least `n_matches` matches after a number of attempts, when each
+/// attempt matches with chance `p_match`.
+pub fn probability_found(n_matches: u64, p_match: f64, attempts: u64) -> f64 {
+    poisson_at_least(n_matches, attempts as f64 * p_match)
+}
+
+/// `P(Poisson(lambda) >= k)`.
+fn poisson_at_least(k: u64, lambda: f64) -> f64 {
+    // Normal approximation once the sum of terms would underflow.
+    if lambda > 500.0 {
+        let z = (k as f64 - 0.5 - lambda) / lambda.sqrt();
+        return 1.0 - standard_normal_cdf(z);
+    }
+    let mut term = (-lambda).exp();
+    let mut below = 0.0;
+    for i in 0..k {
+        below += term;
+        term *= lambda / (i + 1) as f64;
+    }
+    1.0 - below
+}
+
+/// Standard normal cumulative distribution function, per Abramowitz and Stegun 26.2.17.
+fn standard_normal_cdf(z: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.2316419 * z.abs());
+    let density = (-z * z / 2.0).exp() / (2.0 * std::f64::consts::PI).sqrt();
+    let tail = density
+        * t
+        * (0.319381530
+            + t * (-0.356563782 + t * (1.781477937 + t * (-1.821255978 + t * 1.330274429))));
+    if z >= 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+/// Local date and time at which a number of seconds from now will have elapsed.
+pub fn local_time_after(seconds: f64) -> String {
+    if !seconds.is_finite() || seconds > MAX_FORECAST.as_secs_f64() {
+        return format!(
+            "over {} years from now",
+            MAX_FORECAST.as_secs() / 31_536_000
+        );
+    }
+    format_local_time(SystemTime::now() + Duration::from_secs_f64(seconds.max(0.0)))
+}
+
+/// Format a time as `YYYY-MM-DD HH:MM` in the local time zone.
+#[cfg(unix)]
+fn format_local_time(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as libc::time_t;
+    // SAFETY: `tm` is plain old data, and `localtime_r` only writes to the struct it is given.
+    let tm = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&seconds, &mut tm);
+        tm
+    };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min
+    )
+}
+
+/// Format a time as `YYYY-MM-DD HH:MM UTC`, lacking portable access to the local time zone.
+#[cfg(not(unix))]
+fn format_local_time(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let (days, seconds_of_day) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+    // Civil from days, per Howard Hinnant's date algorithms.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance * expected.abs(),
+            "{} is not within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    #[test]
+    fn quantiles_match_the_poisson_distribution() {
+        let p = 1e-6;
+        assert_eq!(attempts_quantile(0, p, 0.5), 0.0);
+        // The first match's attempts are exponential, with quantiles -ln(1 - q) / p.
+        assert_close(attempts_quantile(1, p, 0.5), 2f64.ln() / p, 1e-9);
+        assert_close(attempts_quantile(1, p, 0.95), 20f64.ln() / p, 1e-9);
+        // Otherwise they are gamma distributed, whose median for shape 2 is about 1.678347.
+        assert_close(attempts_quantile(2, p, 0.5), 1.678347 / p, 1e-6);
+        // Past the normal approximation's threshold, the median is half a match short of the count.
+        assert_close(attempts_quantile(1000, p, 0.5), 999.5 / p, 1e-6);
+        assert!(attempts_quantile(1000, p, 0.95) > attempts_quantile(1000, p, 0.5));
+    }
+
+    #[test]
+    fn chances_of_having_found_match_the_poisson_distribution() {
+        assert_eq!(probability_found(0, 1e-6, 0), 1.0);
+        assert_eq!(probability_found(1, 1e-6, 0), 0.0);
+        assert_close(probability_found(1, 1e-6, 693_147), 0.5, 1e-6);
+        // P(Poisson(1) >= 3) = 1 - 2.5 / e.
+        assert_close(probability_found(3, 0.5, 2), 0.080301397071394, 1e-9);
+        // The normal approximation stays close to the exact sum where it takes over.
+        assert_close(probability_found(500, 1.0, 500), 0.505947146, 1e-9);
+        assert_close(probability_found(500, 1.0, 501), 0.523761670, 1e-2);
+    }
+}
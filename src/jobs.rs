@@ -0,0 +1,230 @@
+//! Jobs files for running several independent searches, one after another or concurrently.
+//!
+//! A jobs file is a TOML document with one `[[job]]` table per search, whose keys are the long
+//! names of the search options (with either `-` or `_` separators), for example:
+//!
+//! ```toml
+//! [[job]]
+//! name = "treasury"
+//! prefix = "cafe"
+//! multisig = true
+//! share = 7
+//!
+//! [[job]]
+//! suffix = "beef"
+//! count = 3
+//! share = 3
+//! ```
+//!
+//! Besides `name`, the `share` key is not a search option: when jobs run concurrently, it weighs
+//! how many of the threads each job gets, defaulting to 1. The `OPTIVANITY_*` environment
+//! variables don't apply to jobs, so a jobs file fully describes its searches.
+//!
+//! Only the subset of TOML needed for this is supported: comments, `[[job]]` headers, and
+//! single-line values that are basic strings (in double quotes, with TOML's escape sequences such
+//! as `\"`, `\\`, `\n`, and `\u00e9`), literal strings (in single quotes, taken verbatim, which
+//! suits regular expressions), integers, or booleans.
+
+use anyhow::{bail, Context, Result};
+use std::{fs, path::Path};
+
+/// Value of a job option.
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+/// A single search job.
+pub struct Job {
+    /// Job name, defaulting to its position in the jobs file
+    pub name: String,
+    /// Weight of the job's share of threads when running jobs concurrently
+    pub share: u64,
+    options: Vec<(String, Value)>,
+}
+
+impl Job {
+    /// Command line arguments equivalent to the job options, including a leading binary name.
+    pub fn argv(&self) -> Vec<String> {
+        let mut argv = vec![self.name.clone()];
+        for (key, value) in &self.options {
+            let flag = format!("--{}", key.replace('_', "-"));
+            match value {
+                Value::String(value) => argv.extend([flag, value.clone()]),
+                Value::Integer(value) => argv.extend([flag, value.to_string()]),
+                Value::Boolean(true) => argv.push(flag),
+                Value::Boolean(false) => {}
+            }
+        }
+        argv
+    }
+}
+
+/// Parse a jobs file into its jobs, in order.
+pub fn parse_jobs_file(path: &Path) -> Result<Vec<Job>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("reading jobs file {}", path.display()))?;
+    let jobs = parse_jobs(&contents).with_context(|| format!("parsing {}", path.display()))?;
+    if jobs.is_empty() {
+        bail!("no [[job]] tables in {}", path.display());
+    }
+    Ok(jobs)
+}
+
+fn parse_jobs(contents: &str) -> Result<Vec<Job>> {
+    let mut jobs: Vec<Job> = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let line_number = i + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "[[job]]" {
+            jobs.push(Job {
+                name: format!("job {}", jobs.len() + 1),
+                share: 1,
+                options: vec![],
+            });
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("line {}: expected `[[job]]` or `key = value`", line_number);
+        };
+        let Some(job) = jobs.last_mut() else {
+            bail!("line {}: option outside of a [[job]] table", line_number);
+        };
+        let key = key.trim().to_string();
+        let value = parse_value(value.trim())
+            .with_context(|| format!("line {}: invalid value for '{}'", line_number, key))?;
+        match (key.as_str(), value) {
+            ("name", Value::String(name)) => job.name = name,
+            ("name", _) => bail!("line {}: name must be a string", line_number),
+            ("share", Value::Integer(share)) if share > 0 => job.share = share as u64,
+            ("share", _) => bail!("line {}: share must be a positive integer", line_number),
+            (_, value) => job.options.push((key, value)),
+        }
+    }
+    Ok(jobs)
+}
+
+/// Strip a trailing comment, ignoring `#` characters inside a string, including after an escaped
+/// quote in a basic string.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+fn parse_value(value: &str) -> Result<Value> {
+    Ok(match value {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        _ if value.starts_with('"') => Value::String(parse_basic_string(&value[1..])?),
+        _ if value.starts_with('\'') => {
+            let Some((string, "")) = value[1..].split_once('\'') else {
+                bail!("literal string must end with the line's only other single quote");
+            };
+            Value::String(string.to_string())
+        }
+        _ => Value::Integer(value.replace('_', "").parse()?),
+    })
+}
+
+/// Parse the rest of a basic string after its opening quote, unescaping its escape sequences.
+fn parse_basic_string(rest: &str) -> Result<String> {
+    let mut chars = rest.chars();
+    let mut string = String::new();
+    loop {
+        let c = match chars.next().context("unterminated string")? {
+            '"' => break,
+            '\\' => match chars.next().context("unterminated string")? {
+                '"' => '"',
+                '\\' => '\\',
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                escape @ ('u' | 'U') => {
+                    let n_digits = if escape == 'u' { 4 } else { 8 };
+                    let digits: String = chars.by_ref().take(n_digits).collect();
+                    if digits.len() != n_digits || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                        bail!("\\{} must be followed by {} hex digits", escape, n_digits);
+                    }
+                    char::from_u32(u32::from_str_radix(&digits, 16)?)
+                        .with_context(|| format!("\\{}{} is not a character", escape, digits))?
+                }
+                escape => bail!("unsupported escape sequence \\{}", escape),
+            },
+            c => c,
+        };
+        string.push(c);
+    }
+    if !chars.as_str().is_empty() {
+        bail!("unexpected characters after string");
+    }
+    Ok(string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(value: &str) -> Result<Value> {
+        parse_value(strip_comment(value).trim())
+    }
+
+    fn string(value: &str) -> String {
+        match parse(value).unwrap() {
+            Value::String(string) => string,
+            _ => panic!("not a string: {}", value),
+        }
+    }
+
+    #[test]
+    fn unescapes_basic_strings() {
+        assert_eq!(string(r#""address =~ \"00$\"""#), r#"address =~ "00$""#);
+        assert_eq!(
+            string(r#""a\\b\n\u00e9\U0001F600""#),
+            "a\\b\n\u{e9}\u{1F600}"
+        );
+        assert_eq!(string(r##""\"#\"" # comment"##), "\"#\"");
+    }
+
+    #[test]
+    fn takes_literal_strings_verbatim() {
+        assert_eq!(
+            string(r#"'address =~ "\d+$"' # comment"#),
+            r#"address =~ "\d+$""#
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_strings() {
+        for value in [
+            r#""unterminated"#,
+            r#""trailing\""#,
+            r#""bad \x escape""#,
+            r#""short \u00e""#,
+            r#""surrogate \uD800""#,
+            r#""a" "b""#,
+            r#"'a' 'b'"#,
+        ] {
+            assert!(parse(value).is_err(), "{}", value);
+        }
+    }
+}
@@ -0,0 +1,423 @@
+//! Vanity address search for Aptos accounts, for embedding in other Rust projects such as wallets
+//! and bots without shelling out to the `optivanity` binary.
+//!
+//! [`Searcher::run`] searches for a [`SearchSpec`] on a pool of worker threads, handing each
+//! [`Match`] to a callback until it has enough. The derivation, matching, and worker building
+//! blocks the binary is made of are public too, for callers that need finer control.
+//!
+//! ```no_run
+//! use optivanity::{SearchSpec, Searcher};
+//!
+//! let spec = SearchSpec {
+//!     prefix: Some("cafe".to_string()),
+//!     ..Default::default()
+//! };
+//! Searcher::run(&spec, |found| {
+//!     println!("0x{}", hex::encode(found.address()));
+//!     false
+//! })
+//! .unwrap();
+//! ```
+
+pub mod constraints;
+pub mod matcher;
+pub mod parallelism;
+pub mod rng;
+mod searcher;
+
+pub use searcher::{SearchSpec, Searcher};
+
+use anyhow::{bail, Result};
+use ed25519_dalek::SigningKey;
+use matcher::Matcher;
+use rng::KeyRng;
+use sha3::{Digest, Sha3_256};
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering::Relaxed},
+    mpsc::{Receiver, SyncSender, TrySendError},
+    Arc,
+};
+
+/// Multisig account generation is assumed to take place in first transaction of standard account.
+pub const SEQUENCE_NUMBER_MULTISIG: u64 = 0;
+
+/// Derive Ed25519 public key bytes vector from a reference to a private key.
+pub fn public_key_bytes_vec(private_key_ref: &SigningKey) -> Vec<u8> {
+    Into::<ed25519_dalek::VerifyingKey>::into(private_key_ref)
+        .to_bytes()
+        .to_vec()
+}
+
+/// Scheme identifier for Ed25519 authentication keys in the Aptos codebase.
+pub const ED25519_SCHEME: u8 = 0;
+
+/// Derive authentication key bytes vector from a reference to a private key, with the given
+/// scheme identifier (normally [`ED25519_SCHEME`]).
+pub fn auth_key_bytes_vec(private_key_ref: &SigningKey, scheme_byte: u8) -> Vec<u8> {
+    public_key_auth_key(&public_key_bytes_vec(private_key_ref), scheme_byte)
+}
+
+/// Derive authentication key bytes vector from public key bytes and a scheme identifier.
+pub fn public_key_auth_key(public_key: &[u8], scheme_byte: u8) -> Vec<u8> {
+    let mut h = Sha3_256::new();
+    h.update(public_key);
+    // Append the scheme identifier, then hash the result and return the hash
+    h.update([scheme_byte]);
+    h.finalize().to_vec()
+}
+
+/// Domain separator appended to the creator address when deriving a multisig account address.
+pub const MULTISIG_DOMAIN_SEPARATOR: &str = "aptos_framework::multisig_account";
+
+/// Scheme byte for addresses derived from an object/account seed in the Aptos codebase.
+pub const DERIVE_OBJECT_ADDRESS_FROM_SEED_SCHEME: u8 = 255;
+
+/// Length in bytes of an authentication key, and hence of a creator address.
+pub const AUTH_KEY_LENGTH: usize = 32;
+
+/// Reusable multisig account address preimage for a fixed creator nonce.
+///
+/// Code inspired by the Aptos codebase.
+/// This is reimplemented to avoid adding Aptos as a dependency which adds over 200 other crates.
+/// See https://github.com/aptos-labs/aptos-core/blob/e2c8a6726a9bc4be464a755b47a113771a89e72c/types/src/account_address.rs#L239
+///
+/// The preimage is `creator || domain separator || creator nonce || scheme byte`. Only the creator
+/// changes between attempts, so everything else is assembled once per worker and the creator bytes
+/// are overwritten in place before each hash.
+#[derive(Clone)]
+pub struct MultisigPreimage {
+    buffer: Vec<u8>,
+}
+
+impl MultisigPreimage {
+    /// Assemble the constant seed material for the given domain separator and creator nonce.
+    pub fn new(domain_separator: &str, creator_nonce: u64) -> Self {
+        let mut buffer = vec![0; AUTH_KEY_LENGTH];
+        buffer.extend(domain_separator.as_bytes());
+        buffer.extend(creator_nonce.to_le_bytes());
+        buffer.push(DERIVE_OBJECT_ADDRESS_FROM_SEED_SCHEME);
+        Self { buffer }
+    }
+
+    /// Create the multisig account address for a creator address.
+    pub fn account_address(&mut self, creator: &[u8]) -> Vec<u8> {
+        self.buffer[..AUTH_KEY_LENGTH].copy_from_slice(creator);
+        Sha3_256::digest(&self.buffer).to_vec()
+    }
+}
+
+/// Return `true` if string slice reference indicates a string with an odd number of characters.
+pub fn has_odd_character_count(string_ref: &str) -> bool {
+    string_ref.len() % 2 == 1
+}
+
+/// Convert a char to what hex number it represents.
+pub fn to_byte(c: char) -> u8 {
+    match c {
+        '0' => 0x0,
+        '1' => 0x1,
+        '2' => 0x2,
+        '3' => 0x3,
+        '4' => 0x4,
+        '5' => 0x5,
+        '6' => 0x6,
+        '7' => 0x7,
+        '8' => 0x8,
+        '9' => 0x9,
+        'a' => 0xa,
+        'b' => 0xb,
+        'c' => 0xc,
+        'd' => 0xd,
+        'e' => 0xe,
+        'f' => 0xf,
+        _ => panic!(),
+    }
+}
+
+/// Vanity pattern translated to whole bytes, plus an optional dangling nibble for patterns with an
+/// odd number of characters.
+pub type PatternBytes = (Vec<u8>, Option<u8>);
+
+/// Kind of account a match was found for.
+#[derive(Clone, Copy, Debug)]
+pub enum AccountKind {
+    /// Standard account, whose address is its authentication key
+    Standard,
+    /// Multisig account created by the standard account, at the given address
+    Multisig { address: [u8; AUTH_KEY_LENGTH] },
+}
+
+/// Match transmitted from a search thread to the main thread, only formatted for output there.
+#[derive(Clone, Copy, Debug)]
+pub struct Match {
+    pub private_key: [u8; ed25519_dalek::SECRET_KEY_LENGTH],
+    pub public_key: [u8; ed25519_dalek::PUBLIC_KEY_LENGTH],
+    pub auth_key: [u8; AUTH_KEY_LENGTH],
+    pub kind: AccountKind,
+}
+
+impl Match {
+    /// Address of the account found: the multisig account address for multisig matches, and
+    /// otherwise the authentication key.
+    pub fn address(&self) -> [u8; AUTH_KEY_LENGTH] {
+        match self.kind {
+            AccountKind::Standard => self.auth_key,
+            AccountKind::Multisig { address } => address,
+        }
+    }
+}
+
+/// Translate an optional prefix string to bytes, with the dangling nibble at the end.
+pub fn prefix_bytes(prefix: Option<String>) -> Result<Option<PatternBytes>> {
+    Ok(if let Some(s) = prefix {
+        Some(if has_odd_character_count(&s) {
+            let c = s.chars().last().unwrap();
+            (hex::decode(&s[..s.len() - 1])?, Some(to_byte(c)))
+        } else {
+            (hex::decode(&s)?, None)
+        })
+    } else {
+        None
+    })
+}
+
+/// Translate an optional suffix string to bytes, with the dangling nibble at the start.
+pub fn suffix_bytes(suffix: Option<String>) -> Result<Option<PatternBytes>> {
+    Ok(if let Some(s) = suffix {
+        Some(if has_odd_character_count(&s) {
+            let c = s.chars().next().unwrap();
+            (hex::decode(&s[1..])?, Some(to_byte(c)))
+        } else {
+            (hex::decode(&s)?, None)
+        })
+    } else {
+        None
+    })
+}
+
+/// Return `true` if search bytes match both the prefix and suffix bytes, where specified.
+pub fn is_match(
+    search_bytes: &[u8],
+    prefix: &Option<PatternBytes>,
+    suffix: &Option<PatternBytes>,
+) -> bool {
+    prefix
+        .as_ref()
+        .is_none_or(|prefix| matches_prefix(search_bytes, prefix))
+        && suffix
+            .as_ref()
+            .is_none_or(|suffix| matches_suffix(search_bytes, suffix))
+}
+
+/// Return `true` if search bytes start with the prefix bytes, then its dangling nibble if any.
+pub fn matches_prefix(search_bytes: &[u8], (pb, pc): &PatternBytes) -> bool {
+    if !search_bytes.starts_with(pb) {
+        return false;
+    }
+    pc.is_none_or(|pc| search_bytes[pb.len()] >> 4 == pc)
+}
+
+/// Return `true` if search bytes end with the suffix bytes, after its dangling nibble if any.
+pub fn matches_suffix(search_bytes: &[u8], (sb, sc): &PatternBytes) -> bool {
+    if !search_bytes.ends_with(sb) {
+        return false;
+    }
+    sc.is_none_or(|sc| search_bytes[search_bytes.len() - sb.len() - 1] & 0x0f == sc)
+}
+
+/// Capacity, in batches, of the bounded channel carrying matches to the main thread.
+pub const MATCH_CHANNEL_CAPACITY: usize = 64;
+
+/// Number of matches a worker accumulates while the match channel is full before blocking.
+pub const MATCH_BATCH_SIZE: usize = 256;
+
+/// Worker-local buffer of matches pending transmission to the main thread.
+///
+/// Matches are handed off without blocking whenever the bounded match channel has room. For easy
+/// patterns where matches arrive faster than the main thread can output them, they accumulate into
+/// a batch instead, and the worker only blocks once a full batch is pending, so memory is bounded
+/// by the channel capacity times the batch size.
+pub struct MatchBatch {
+    match_tx: SyncSender<Vec<Match>>,
+    pending: Vec<Match>,
+    batch_size: usize,
+}
+
+impl MatchBatch {
+    pub fn new(match_tx: SyncSender<Vec<Match>>, batch_size: usize) -> Self {
+        Self {
+            match_tx,
+            pending: vec![],
+            batch_size,
+        }
+    }
+
+    /// Queue a match for a private key, its authentication key, and for multisig
+    /// searches the multisig account address it matched on, unless the key is weak.
+    pub fn push(
+        &mut self,
+        private_key: &SigningKey,
+        auth_key: Vec<u8>,
+        multisig_address: Option<Vec<u8>>,
+    ) -> Result<()> {
+        // Defense in depth: never emit a key whose public key is a small-order point, so that the
+        // worker moves on to a fresh key instead.
+        if private_key.verifying_key().is_weak() {
+            return self.flush();
+        }
+        self.pending.push(Match {
+            private_key: private_key.to_bytes(),
+            public_key: private_key.verifying_key().to_bytes(),
+            auth_key: auth_key.as_slice().try_into()?,
+            kind: match multisig_address {
+                Some(address) => AccountKind::Multisig {
+                    address: address.as_slice().try_into()?,
+                },
+                None => AccountKind::Standard,
+            },
+        });
+        self.flush()
+    }
+
+    /// Try to transmit pending matches, blocking only if a full batch is pending.
+    pub fn flush(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        match self.match_tx.try_send(std::mem::take(&mut self.pending)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(batch)) if batch.len() < self.batch_size => {
+                self.pending = batch;
+                Ok(())
+            }
+            Err(TrySendError::Full(batch)) => Ok(self.match_tx.send(batch)?),
+            Err(TrySendError::Disconnected(_)) => bail!("match channel disconnected"),
+        }
+    }
+}
+
+/// Generate a private key corresponding to a vanity prefix, while search is ongoing.
+///
+/// Once a match is found, a match message is transmitted to the main thread. Once the main thread
+/// has received sufficient match transactions, it will exit making all the other threads stop.
+///
+/// # Arguments
+///
+/// * `prefix` - The vanity prefix to search against
+/// * `suffix` - The vanity suffix to search against
+/// * `creator` - Checks on the creating standard account's address, for multisig searches
+/// * `scheme_byte` - Scheme identifier to derive authentication keys with
+/// * `multisig_preimage` - Multisig preimage to derive addresses with, if searching for multisig
+///   addresses
+/// * `rng` - Source of private keys
+/// * `matches` - Buffer of matches sent to main thread
+/// * `counter` - Atomic integer that keeps track of the total number of addresses generated
+/// * `stop` - Atomic flag set by the main thread once the search is over
+#[allow(clippy::too_many_arguments)]
+pub fn generate_key(
+    prefix: Option<String>,
+    suffix: Option<String>,
+    creator: Matcher,
+    scheme_byte: u8,
+    mut multisig_preimage: Option<MultisigPreimage>,
+    mut rng: KeyRng,
+    mut matches: MatchBatch,
+    counter: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+) -> Result<()> {
+    let matcher = Matcher::new(prefix_bytes(prefix)?, suffix_bytes(suffix)?);
+
+    // Randomly generate private keys in a loop and check match against prefix bytes.
+    while !stop.load(Relaxed) {
+        // Generate a private key and from it, bytes to compare against prefix bytes.
+        let private_key = rng.generate_key()?;
+        let auth_key = auth_key_bytes_vec(&private_key, scheme_byte);
+
+        // Increment generated addresses counter
+        counter.fetch_add(1, Relaxed);
+
+        // Only spend the second hash of a multisig search on creators that pass their own checks.
+        if !creator.is_match(&auth_key) {
+            matches.flush()?;
+            continue;
+        }
+        let multisig_address = multisig_preimage
+            .as_mut()
+            .map(|preimage| preimage.account_address(&auth_key));
+        let search_bytes = multisig_address.as_ref().unwrap_or(&auth_key);
+
+        if matcher.is_match(search_bytes) {
+            matches.push(&private_key, auth_key, multisig_address)?;
+        } else {
+            matches.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// First stage of a pipelined multisig search: generate private keys and their authentication
+/// keys, handing those whose creator address passes its checks off to the hashing stage over a
+/// bounded queue.
+///
+/// Exits once the search is over, or the hashing stage hangs up.
+pub fn generate_auth_keys(
+    scheme_byte: u8,
+    creator: Matcher,
+    mut rng: KeyRng,
+    auth_key_tx: SyncSender<(SigningKey, Vec<u8>)>,
+    counter: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+) -> Result<()> {
+    while !stop.load(Relaxed) {
+        let private_key = rng.generate_key()?;
+        let auth_key = auth_key_bytes_vec(&private_key, scheme_byte);
+        // Count creators rejected here, since the hashing stage never sees them.
+        if !creator.is_match(&auth_key) {
+            counter.fetch_add(1, Relaxed);
+            continue;
+        }
+        auth_key_tx.send((private_key, auth_key))?;
+    }
+    Ok(())
+}
+
+/// Second stage of a pipelined multisig search: derive multisig account addresses from the
+/// authentication keys produced by the first stage, and check them for a match.
+///
+/// # Arguments
+///
+/// * `prefix` - The vanity prefix to search against
+/// * `suffix` - The vanity suffix to search against
+/// * `multisig_preimage` - Multisig preimage to derive addresses with
+/// * `auth_key_rx` - Receive channel for private keys and authentication keys from first stage
+/// * `matches` - Buffer of matches sent to main thread
+/// * `counter` - Atomic integer that keeps track of the total number of addresses generated
+/// * `stop` - Atomic flag set by the main thread once the search is over
+pub fn match_multisig_addresses(
+    prefix: Option<String>,
+    suffix: Option<String>,
+    mut multisig_preimage: MultisigPreimage,
+    auth_key_rx: Receiver<(SigningKey, Vec<u8>)>,
+    mut matches: MatchBatch,
+    counter: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+) -> Result<()> {
+    let matcher = Matcher::new(prefix_bytes(prefix)?, suffix_bytes(suffix)?);
+
+    for (private_key, auth_key) in auth_key_rx {
+        if stop.load(Relaxed) {
+            break;
+        }
+        let search_bytes = multisig_preimage.account_address(&auth_key);
+
+        // Increment generated addresses counter
+        counter.fetch_add(1, Relaxed);
+
+        if matcher.is_match(&search_bytes) {
+            matches.push(&private_key, auth_key, Some(search_bytes))?;
+        } else {
+            matches.flush()?;
+        }
+    }
+    Ok(())
+}
@@ -1,313 +1,2252 @@
-use anyhow::{bail, Result};
-use clap::Parser;
+mod analyze;
+mod attempts;
+mod audit;
+mod bench;
+mod commitment;
+mod dedupe;
+mod derivable;
+mod derive_batch;
+#[cfg(feature = "distributed")]
+mod distributed;
+mod doctor;
+mod estimate;
+mod filter;
+mod forecast;
+mod jobs;
+mod manifest;
+mod modes;
+#[cfg(feature = "notifications")]
+mod mqtt;
+mod multi_ed25519;
+mod offline;
+mod operator;
+mod partition;
+mod sampling;
+mod session;
+mod shamir;
+mod simulate;
+mod sink;
+mod soak;
+mod stats;
+mod throughput;
+mod warnings;
+#[cfg(feature = "wizard")]
+mod wizard;
+mod wordlists;
+
+use anyhow::{bail, Context, Result};
+use attempts::{pattern_key, search_key, AttemptsFile};
+use audit::AuditLog;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use commitment::CommitmentKey;
+use dedupe::DedupeDb;
+#[cfg(feature = "distributed")]
+use distributed::CoordinatorLink;
 use ed25519_dalek::SigningKey;
-use num::{BigInt, FromPrimitive};
+use filter::Filter;
+use manifest::Manifest;
+#[cfg(feature = "notifications")]
+use mqtt::{Delivery, MatchDigest, MqttPublisher};
+use operator::OperatorKey;
+use optivanity::{
+    auth_key_bytes_vec,
+    constraints::{self, Constraint, ADDRESS_LENGTH_NIBBLES},
+    generate_auth_keys, generate_key, is_match, match_multisig_addresses,
+    matcher::Matcher,
+    parallelism::{self, default_threads},
+    prefix_bytes, public_key_auth_key, public_key_bytes_vec,
+    rng::{KeyRng, ReseedPolicy},
+    suffix_bytes, AccountKind, Match, MatchBatch, MultisigPreimage, AUTH_KEY_LENGTH,
+    DERIVE_OBJECT_ADDRESS_FROM_SEED_SCHEME, ED25519_SCHEME, MATCH_BATCH_SIZE,
+    MATCH_CHANNEL_CAPACITY, MULTISIG_DOMAIN_SEPARATOR, SEQUENCE_NUMBER_MULTISIG,
+};
+use partition::PartitionSpec;
 use regex::Regex;
-use sha3::{Digest, Sha3_256};
+use sampling::{OutputSampler, SampleSpec};
+use session::{Session, SessionLog};
+use shamir::ShamirSpec;
+use simulate::Simulation;
+#[cfg(feature = "distributed")]
+use sink::CoordinatorSink;
+#[cfg(feature = "notifications")]
+use sink::MqttSink;
+use sink::{
+    AuditSink, ConsoleSink, FileSink, JsonSink, MatchJson, MatchRecord, OutputSink, OutputSpec,
+    ShuffleSink,
+};
+use stats::Stats;
 use std::{
+    any::Any,
+    fs::File,
+    io::{BufRead, BufReader, IsTerminal, Write},
+    path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicU64, Ordering::Relaxed},
-        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering::Relaxed},
+        mpsc::{Receiver, SyncSender},
+        Arc, Mutex,
     },
-    thread::{self, available_parallelism},
+    thread::{self, JoinHandle},
     time::{Duration, Instant},
 };
+use throughput::{ThroughputAlert, ThroughputMonitor};
+use warnings::WarningFormat;
+use wordlists::Language;
+
+/// SDK snippet formats that can be printed alongside each match.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    /// Aptos TypeScript SDK (`@aptos-labs/ts-sdk`)
+    TsSdk,
+    /// Aptos Python SDK (`aptos-sdk`)
+    PySdk,
+}
+
+/// Form in which to print account addresses.
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+enum AddressFormat {
+    /// All 64 hex characters
+    #[default]
+    Long,
+    /// Leading zeros trimmed
+    Short,
+    /// AIP-40 canonical form: short for reserved addresses `0x0` through `0xf`, long otherwise
+    Aip40,
+}
+
+impl AddressFormat {
+    /// Format an address, given as hex without a leading `0x`, with a leading `0x`.
+    fn format(self, address: &str) -> String {
+        let long = format!("{:0>width$}", address, width = ADDRESS_LENGTH_NIBBLES);
+        let trimmed = long.trim_start_matches('0');
+        let short = if trimmed.is_empty() { "0" } else { trimmed };
+        match self {
+            Self::Long => format!("0x{}", long),
+            Self::Aip40 if short.len() > 1 => format!("0x{}", long),
+            Self::Short | Self::Aip40 => format!("0x{}", short),
+        }
+    }
+}
 
-/// Multisig account generation is assumed to take place in first transaction of standard account.
-const SEQUENCE_NUMBER_MULTISIG: u64 = 0;
+/// How much of each private key to reveal in output.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Redaction {
+    /// Show private keys in full
+    None,
+    /// Show only the first and last four characters of private keys, to tell them apart
+    Partial,
+    /// Never show private keys
+    Full,
+}
 
 /// Optivanity: hyper-parallelized vanity address generator for the Aptos blockchain, brought to you
 /// by Econia Labs
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    search: CliArgs,
+}
+
+/// Subcommands, in place of a single search configured by the top-level options.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run each search job in a TOML jobs file one after another, then summarize them all
+    Run {
+        /// Jobs file, with a `[[job]]` table per search taking the same options as a single search
+        jobs: PathBuf,
+        /// Run all jobs at once instead, splitting threads between them in proportion to each
+        /// job's `share` (1 by default), in place of their own thread counts
+        #[arg(long)]
+        concurrent: bool,
+        /// Number of threads to split between concurrent jobs
+        #[arg(
+            short,
+            long,
+            requires = "concurrent",
+            default_value_t = default_threads()
+        )]
+        threads: usize,
+        /// Session file recording each job's matches. If it already exists, resume the session,
+        /// skipping jobs that already found their count and searching only for the rest of the
+        /// others' matches.
+        #[arg(long)]
+        session: Option<PathBuf>,
+    },
+    /// Interactively configure a search, with live difficulty feedback, then run it
+    #[cfg(feature = "wizard")]
+    Wizard,
+    /// Recover a private key from Shamir share files written with `--shamir`
+    Recover {
+        /// Share files, at least as many as the threshold the key was split with
+        #[arg(required = true)]
+        shares: Vec<PathBuf>,
+        /// Advanced: scheme identifier the key's account address was derived with, if the search
+        /// used a different `--scheme-byte`
+        #[arg(long, default_value_t = ED25519_SCHEME)]
+        scheme_byte: u8,
+    },
+    /// Derive the standard and multisig addresses of each public key in a CSV file, writing them
+    /// as CSV to standard output
+    DeriveBatch {
+        /// CSV file with hex public keys in its first column, optionally under a header row
+        #[arg(long)]
+        input: PathBuf,
+        /// Creator nonces to derive multisig addresses for, as `START..END` (end exclusive) or a
+        /// single nonce
+        #[arg(long, default_value = "0..1", value_parser = derive_batch::parse_nonce_range)]
+        multisig_nonces: std::ops::Range<u64>,
+        /// Advanced: scheme identifier appended to public keys when deriving authentication keys
+        #[arg(long, default_value_t = ED25519_SCHEME)]
+        scheme_byte: u8,
+        /// Advanced: domain separator used when deriving multisig account addresses
+        #[arg(long, default_value = MULTISIG_DOMAIN_SEPARATOR)]
+        multisig_domain: String,
+        /// Form in which to output account addresses
+        #[arg(long, value_enum, default_value_t = AddressFormat::Long)]
+        address_format: AddressFormat,
+    },
+    /// Benchmark address generation throughput, optionally against a stored baseline
+    Bench {
+        /// Number of threads to benchmark across
+        #[arg(short, long, default_value_t = default_threads())]
+        threads: usize,
+        /// Seconds to sample each of standard and multisig address generation for
+        #[arg(long, default_value_t = 5)]
+        seconds: u64,
+        /// Baseline to compare against, exiting nonzero if throughput has regressed
+        #[arg(long)]
+        baseline: Option<PathBuf>,
+        /// Save the measured throughput as a baseline to this file
+        #[arg(long)]
+        save_baseline: Option<PathBuf>,
+        /// Largest tolerated drop in throughput below the baseline, in percent
+        #[arg(long, default_value_t = 10.0)]
+        max_regression: f64,
+    },
+    /// Run address generation under full load for hours, checking every derivation against an
+    /// independent recomputation, to validate hardware before trusting its output
+    Soak {
+        /// Hours to run for
+        #[arg(long, default_value_t = 8.0)]
+        hours: f64,
+        /// Number of threads to load
+        #[arg(short, long, default_value_t = default_threads())]
+        threads: usize,
+    },
+    /// Score existing addresses by how rare their repeated characters and hexspeak words are
+    Analyze {
+        /// Addresses to analyze
+        #[arg(required = true)]
+        addresses: Vec<String>,
+        /// Built-in wordlists to look for words from
+        #[arg(long, value_delimiter = ',', default_value = "english")]
+        dictionary_lang: Vec<Language>,
+        /// File of extra words to look for, one per line, merged with the built-in wordlists
+        #[arg(long, value_name = "PATH")]
+        wordlist: Option<PathBuf>,
+    },
+    /// Compare how long searches for several candidate patterns would take on this machine
+    Estimate {
+        /// Candidate patterns, each a prefix, or `PREFIX..SUFFIX` to also match a suffix
+        #[arg(required = true)]
+        patterns: Vec<String>,
+        /// Estimate multisig address searches
+        #[arg(short, long)]
+        multisig: bool,
+        /// Number of vanity accounts to generate
+        #[arg(short, long, default_value_t = 1)]
+        count: u64,
+        /// Number of threads to estimate for
+        #[arg(short, long, default_value_t = default_threads())]
+        threads: usize,
+        /// Cost of running this machine for an hour, to also show the expected cost of each search
+        #[arg(long)]
+        cost_per_hour: Option<f64>,
+    },
+    /// Search orderings of a fixed set of MultiEd25519 owners for a vanity authentication key
+    MultiEd25519 {
+        /// Owner public key, as hex. Repeat for each owner (or candidate owner).
+        #[arg(long = "owner", required = true)]
+        owners: Vec<String>,
+        /// Number of signatures required by the account
+        #[arg(long)]
+        threshold: u8,
+        /// Also search over which this many of the given keys become owners
+        #[arg(long)]
+        subset_size: Option<usize>,
+        /// Authentication key prefix to match (no leading `0x`)
+        #[arg(short, long)]
+        prefix: Option<String>,
+        /// Authentication key suffix to match
+        #[arg(short, long)]
+        suffix: Option<String>,
+        /// Number of threads to use
+        #[arg(short, long, default_value_t = default_threads())]
+        threads: usize,
+    },
+    /// Search salts of an AIP-113 derivable account's abstract public key for a vanity address
+    Derivable {
+        /// Authentication function of the account, as `ADDRESS::MODULE::FUNCTION`
+        #[arg(long)]
+        function: String,
+        /// Fixed leading bytes of the abstract public key, as hex
+        #[arg(long, default_value = "")]
+        key_prefix: String,
+        /// Number of salt bytes to search over, appended to the fixed leading bytes
+        #[arg(long, default_value_t = 8)]
+        salt_bytes: usize,
+        /// First salt to search, to split the salt space into ranges searched separately
+        #[arg(long, default_value_t = 0)]
+        first_salt: u64,
+        /// Number of salts to search from the first, rather than the rest of the salt space
+        #[arg(long)]
+        salt_count: Option<u64>,
+        /// Address prefix to match (no leading `0x`)
+        #[arg(short, long)]
+        prefix: Option<String>,
+        /// Address suffix to match
+        #[arg(short, long)]
+        suffix: Option<String>,
+        /// Number of threads to use
+        #[arg(short, long, default_value_t = default_threads())]
+        threads: usize,
+    },
+    /// Coordinate a search across worker machines, handing each worker that connects the search
+    /// given after `--`, and stopping them all once its count of matches is found
+    #[cfg(feature = "distributed")]
+    Serve {
+        /// Address to listen for workers on, only local by default
+        #[arg(long, default_value = "127.0.0.1:7878")]
+        listen: String,
+        /// Token shared with workers, which each side proves it knows before the search is handed
+        /// out
+        #[arg(long, env = "OPTIVANITY_FLEET_TOKEN", hide_env_values = true)]
+        token: String,
+        /// Options defining the search for every worker to run, after `--`, among --prefix,
+        /// --suffix, --multisig, --count, --creator-prefix, and --creator-suffix, for example
+        /// `-- --prefix cafe`
+        #[arg(last = true, required = true)]
+        search: Vec<String>,
+    },
+    /// Run the search handed out by a coordinator started with `serve`, reporting progress and
+    /// matches to it, and keeping private keys on this machine
+    #[cfg(feature = "distributed")]
+    Worker {
+        /// Address of the coordinator, as `HOST:PORT`
+        #[arg(long)]
+        connect: String,
+        /// Token shared with the coordinator, which each side proves it knows before the search
+        /// is handed out
+        #[arg(long, env = "OPTIVANITY_FLEET_TOKEN", hide_env_values = true)]
+        token: String,
+        /// Number of threads to use, in place of the search's own thread count
+        #[arg(short, long)]
+        threads: Option<usize>,
+        /// This worker's own search options, after `--`, such as where to output matches or how
+        /// addresses are derived, which the coordinator can't set
+        #[arg(last = true)]
+        local: Vec<String>,
+    },
+    /// Compact a dedupe database, for example after concatenating several, so that it records
+    /// each address once
+    Compact {
+        /// Dedupe database written with `--dedupe-db`
+        path: PathBuf,
+    },
+    /// Report this build's features, the CPU features, parallelism, and entropy source of this
+    /// machine, and its throughput, for debugging performance reports
+    Doctor,
+    /// List the combinations of chain, signature scheme, derivation, and backend this build can
+    /// search
+    Modes,
+    /// Verify that an audit log's hash chain is intact
+    VerifyAuditLog {
+        /// Audit log written with `--audit-log`
+        path: PathBuf,
+    },
+}
+
+/// Options for a single vanity address search.
+#[derive(Parser, Clone, Debug)]
 struct CliArgs {
     /// Address prefix to match (no leading `0x`). Each additional character slows search by 16x.
-    #[arg(short, long)]
+    #[arg(short, long, env = "OPTIVANITY_PREFIX")]
     prefix: Option<String>,
     /// Address suffix to match. Each additional character slows search by 16x.
-    #[arg(short, long)]
+    #[arg(short, long, env = "OPTIVANITY_SUFFIX")]
     suffix: Option<String>,
+    /// Literal address target, up to the full 64 characters, to match from the start of the
+    /// address. Asks for confirmation, since long targets are practically impossible to find.
+    #[arg(
+        long,
+        value_name = "ADDRESS_PREFIX",
+        conflicts_with = "prefix",
+        env = "OPTIVANITY_EXACT"
+    )]
+    exact: Option<String>,
+    /// Search for each pattern in a file one after another, tagging matches with their pattern.
+    /// Patterns are one per line, each a prefix or `PREFIX..SUFFIX`. `-` reads them from standard
+    /// input as they arrive, for piping in from another tool such as a wordlist generator.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["prefix", "suffix", "exact"],
+        env = "OPTIVANITY_PATTERNS"
+    )]
+    patterns: Option<PathBuf>,
+    /// Confirm an `--exact` search up front, for non-interactive use
+    #[arg(long, requires = "exact", env = "OPTIVANITY_CONFIRM_EXACT")]
+    confirm_exact: bool,
     /// Use this flag if you want to search for multisig address(es)
-    #[arg(short, long)]
+    #[arg(short, long, env = "OPTIVANITY_MULTISIG")]
     multisig: bool,
+    /// With `--multisig`, also require the creating standard account's address to start with this
+    /// prefix. Creators that don't match are rejected before their multisig address is hashed.
+    #[arg(long, requires = "multisig", env = "OPTIVANITY_CREATOR_PREFIX")]
+    creator_prefix: Option<String>,
+    /// With `--multisig`, also require the creating standard account's address to end with this
+    /// suffix
+    #[arg(long, requires = "multisig", env = "OPTIVANITY_CREATOR_SUFFIX")]
+    creator_suffix: Option<String>,
+    /// Address of an additional multisig owner besides the creating standard account. Repeat for
+    /// each owner. Included with each match, along with the threshold and creator nonce, so the
+    /// creation transaction can be built without re-entering them.
+    #[arg(
+        long = "multisig-owner",
+        value_name = "ADDRESS",
+        value_delimiter = ',',
+        requires_all = ["multisig", "multisig_threshold"],
+        env = "OPTIVANITY_MULTISIG_OWNERS"
+    )]
+    multisig_owners: Vec<String>,
+    /// Number of owner signatures the multisig account will require
+    #[arg(long, requires = "multisig", env = "OPTIVANITY_MULTISIG_THRESHOLD")]
+    multisig_threshold: Option<u64>,
+    /// Search only this machine's partition of the address space, for example 3/16 on the fourth
+    /// of sixteen machines, so that no two machines can emit the same address. Each partition's
+    /// addresses continue the prefix with the partition index in hex, so the partition count must
+    /// be a power of 16.
+    #[arg(
+        long,
+        value_name = "INDEX/COUNT",
+        conflicts_with = "exact",
+        env = "OPTIVANITY_PARTITION"
+    )]
+    partition: Option<PartitionSpec>,
     /// Number of vanity accounts to generate
-    #[arg(short, long, default_value_t = 1)]
+    #[arg(short, long, default_value_t = 1, env = "OPTIVANITY_COUNT")]
     count: u64,
     /// Number of threads to use. Only specify if you want to use fewer cores than available.
-    #[arg(short, long, default_value_t = available_parallelism().unwrap().get())]
+    #[arg(
+        short,
+        long,
+        default_value_t = default_threads(),
+        env = "OPTIVANITY_THREADS"
+    )]
     threads: usize,
+    /// Also print a ready-to-paste SDK snippet loading each found private key
+    #[arg(
+        short,
+        long,
+        value_enum,
+        env = "OPTIVANITY_EXPORT",
+        conflicts_with = "shamir"
+    )]
+    export: Option<ExportFormat>,
+    /// Pipeline multisig searches across pairs of threads, one generating authentication keys and
+    /// one deriving multisig addresses, with up to this many keys queued between the two
+    #[arg(long, env = "OPTIVANITY_PIPELINE_DEPTH")]
+    pipeline_depth: Option<usize>,
+    /// File recording cumulative attempts per search, so repeated or resumed runs of the same
+    /// search report the total effort spent on it. Searches are told apart by their patterns,
+    /// including creator patterns, and derivation settings.
+    #[arg(long, env = "OPTIVANITY_ATTEMPTS_FILE")]
+    attempts_file: Option<PathBuf>,
+    /// Warn when throughput stays more than this many percent below its baseline for 30 seconds,
+    /// for example due to thermal throttling or CPU contention (0 to disable)
+    #[arg(
+        long,
+        default_value_t = 30,
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        env = "OPTIVANITY_THROUGHPUT_ALERT_DROP"
+    )]
+    throughput_alert_drop: u8,
+    /// Append a hash-chained record of each emitted address (with a public key fingerprint, never
+    /// the private key) to this audit log
+    #[arg(long, env = "OPTIVANITY_AUDIT_LOG")]
+    audit_log: Option<PathBuf>,
+    /// Where to output each match: `console`, `json` or `ndjson` to print it as JSON (in an array
+    /// once the run is over, or a line at a time) to standard output in place of any plain-text
+    /// output and progress display, or `file:PATH` to append it as a line of JSON to a file.
+    /// Repeat, or separate with commas, to output each match to several destinations.
+    #[arg(
+        long,
+        value_name = "SINK",
+        value_delimiter = ',',
+        default_value = "console",
+        env = "OPTIVANITY_OUTPUT"
+    )]
+    output: Vec<OutputSpec>,
+    /// Hold back every match until the run is over, then output them all in a random order, so
+    /// that output order doesn't reveal the order or timing in which they were generated. Matches
+    /// are output with the run's average attempts and search time per match, and signed with any
+    /// `--operator-key` as they are output. Matches found so far are lost if the run is killed.
+    #[arg(long, env = "OPTIVANITY_SHUFFLE_OUTPUT")]
+    shuffle_output: bool,
+    /// Record each emitted address in this dedupe database, and skip addresses already recorded
+    /// there, for example by a run being resumed or by another machine's run
+    #[arg(long, env = "OPTIVANITY_DEDUPE_DB")]
+    dedupe_db: Option<PathBuf>,
+    /// Write a JSON manifest of the run's exact configuration, binary version, hardware, and output
+    /// format versions to this file, updated with the outcome when the run finishes
+    #[arg(long, env = "OPTIVANITY_MANIFEST")]
+    manifest: Option<PathBuf>,
+    /// Split each private key into Shamir shares, any THRESHOLD of which recover it (for example
+    /// 3/5), writing each share to its own file instead of printing the key
+    #[arg(long, value_name = "THRESHOLD/SHARES", env = "OPTIVANITY_SHAMIR")]
+    shamir: Option<ShamirSpec>,
+    /// How much of each private key to reveal in every output, for example to keep keys out of
+    /// terminal scrollback. Requires `--shamir`, so the full key is still kept, as shares.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Redaction::None,
+        env = "OPTIVANITY_REDACT"
+    )]
+    redact: Redaction,
+    /// Buyer-supplied secret key, as hex, to output an HMAC-SHA3-256 commitment of each private key
+    /// under, so a seller can prove they found a match before revealing its private key
+    #[arg(long, value_name = "HEX_KEY", env = "OPTIVANITY_COMMIT_KEY")]
+    commit_key: Option<CommitmentKey>,
+    /// File holding an operator's Ed25519 private key, as hex, to sign each emitted result (its
+    /// address, run UUID, and timestamp, never its private key) with, so downstream consumers can
+    /// authenticate where results came from
+    #[arg(long, env = "OPTIVANITY_OPERATOR_KEY")]
+    operator_key: Option<PathBuf>,
+    /// Directory to write Shamir share files to. Repeat, or separate with commas, to write each
+    /// share to the next directory in turn, for example one per drive.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = ".",
+        env = "OPTIVANITY_SHAMIR_DIR"
+    )]
+    shamir_dir: Vec<PathBuf>,
+    /// Publish progress and match events (never private keys) to an MQTT broker at `host[:port]`,
+    /// under the given base topic
+    #[cfg(feature = "notifications")]
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["BROKER", "TOPIC"],
+        value_delimiter = ' ',
+        env = "OPTIVANITY_MQTT"
+    )]
+    mqtt: Option<Vec<String>>,
+    /// With `--mqtt`, publish matches at most once per this many minutes, as a digest of the
+    /// matches since the previous one, rather than a message per match. Completion is always
+    /// published.
+    #[cfg(feature = "notifications")]
+    #[arg(long, requires = "mqtt", env = "OPTIVANITY_MQTT_DIGEST_MINUTES")]
+    mqtt_digest_minutes: Option<u64>,
+    /// Refuse to start if any network-touching option is configured, forbid the search from
+    /// creating network sockets (on Linux), and fail the run if a network socket is ever found
+    /// open, for provably air-gapped key generation
+    #[arg(long, env = "OPTIVANITY_OFFLINE")]
+    #[cfg_attr(feature = "notifications", arg(conflicts_with = "mqtt"))]
+    offline: bool,
+    /// Stop early, keeping any matches found so far, once there is this chance (between 0 and 1)
+    /// that all requested matches should have been found, for budgeting best-effort searches
+    #[arg(long, value_parser = parse_probability, env = "OPTIVANITY_UNTIL_PROBABILITY")]
+    until_probability: Option<f64>,
+    /// Stop after generating this many addresses, keeping any matches found so far, with a progress
+    /// bar toward this budget in place of the spinner
+    #[arg(long, env = "OPTIVANITY_MAX_ATTEMPTS")]
+    max_attempts: Option<u64>,
+    /// Optimize purely for the time to the first match, for scripts invoking this many times for
+    /// short patterns: start every thread at once, and skip progress display, sampling, and
+    /// forecasts
+    #[arg(
+        long,
+        conflicts_with_all = ["count", "until_probability", "max_attempts"],
+        env = "OPTIVANITY_FIRST_MATCH_FAST"
+    )]
+    #[cfg_attr(feature = "notifications", arg(conflicts_with = "mqtt"))]
+    first_match_fast: bool,
+    /// Fake the search rather than generating keys, at `rate=RATE,hit-after=DURATION` (for
+    /// example `rate=2M,hit-after=30s`), driving progress, telemetry, and outputs as a real
+    /// search would, for developing integrations. Matches are random keys that don't match the
+    /// pattern.
+    #[arg(long, value_name = "SIMULATION", env = "OPTIVANITY_SIMULATE")]
+    simulate: Option<Simulation>,
+    /// Label tagging every result, log record, and telemetry payload of this run, along with a
+    /// generated run UUID, so results can be traced back to their purpose later
+    #[arg(long, value_parser = parse_label, env = "OPTIVANITY_LABEL")]
+    label: Option<String>,
+    /// Only output (and count) matches passing this filter, as `FIELD OP VALUE`, for example
+    /// `'attempts < 1000000'` or `'address =~ "00$"'`. Repeat to require several filters.
+    /// Numeric fields: attempts, elapsed_ms, total_attempts, total_elapsed_ms. Text fields:
+    /// address, auth_key, public_key.
+    #[arg(long, value_name = "FILTER", env = "OPTIVANITY_FILTER")]
+    filter: Vec<Filter>,
+    /// For trivial patterns, only output (and count) a sample of the matches, for example 1/100
+    #[arg(long, value_name = "KEEP/EVERY", env = "OPTIVANITY_SAMPLE")]
+    sample: Option<SampleSpec>,
+    /// For trivial patterns, skip matches beyond this many per second
+    #[arg(
+        long,
+        value_name = "RATE",
+        value_parser = parse_rate,
+        env = "OPTIVANITY_MAX_RESULTS_PER_SECOND"
+    )]
+    max_results_per_second: Option<f64>,
+    /// Cap on memory use in MiB, to which the queues between threads are sized, preventing OOM
+    /// kills during long unattended runs on small machines
+    #[arg(long, value_name = "MIB", env = "OPTIVANITY_MAX_MEMORY")]
+    max_memory: Option<u64>,
+    /// Preset for 1-core, 512 MB machines: a single thread, no pipelining, and a 64 MiB memory cap
+    /// unless `--max-memory` is given
+    #[arg(
+        long,
+        conflicts_with_all = ["threads", "pipeline_depth"],
+        env = "OPTIVANITY_LITE"
+    )]
+    lite: bool,
+    /// Generate private keys from a fast per-thread generator seeded from the operating system,
+    /// instead of drawing each key from the operating system. The generator is rekeyed from its own
+    /// output every 1024 keys, so a leaked generator state can't reveal earlier keys.
+    #[arg(long, env = "OPTIVANITY_BUFFERED_RNG")]
+    buffered_rng: bool,
+    /// With `--buffered-rng`, reseed from the operating system after generating this many keys
+    #[arg(
+        long,
+        default_value_t = 1 << 20,
+        requires = "buffered_rng",
+        env = "OPTIVANITY_RESEED_KEYS"
+    )]
+    reseed_keys: u64,
+    /// With `--buffered-rng`, reseed from the operating system after this many seconds
+    #[arg(
+        long,
+        default_value_t = 60,
+        requires = "buffered_rng",
+        env = "OPTIVANITY_RESEED_SECONDS"
+    )]
+    reseed_seconds: u64,
+    /// Form in which to output account addresses, for the downstream tools consuming them
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = AddressFormat::Long,
+        env = "OPTIVANITY_ADDRESS_FORMAT"
+    )]
+    address_format: AddressFormat,
+    /// Form in which to print warnings: highlighted text, or JSON lines for a supervising process
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = WarningFormat::Text,
+        env = "OPTIVANITY_WARNING_FORMAT"
+    )]
+    warning_format: WarningFormat,
+    /// Advanced: scheme identifier appended to public keys when deriving authentication keys, for
+    /// forks and testnets that changed it
+    #[arg(long, default_value_t = ED25519_SCHEME, env = "OPTIVANITY_SCHEME_BYTE")]
+    scheme_byte: u8,
+    /// Advanced: domain separator used when deriving multisig account addresses, for forks and
+    /// testnets that changed it
+    #[arg(
+        long,
+        default_value = MULTISIG_DOMAIN_SEPARATOR,
+        env = "OPTIVANITY_MULTISIG_DOMAIN"
+    )]
+    multisig_domain: String,
+    /// Session file and job name to record each emitted match under, when run from a jobs file
+    #[arg(skip)]
+    session: Option<(PathBuf, String)>,
+    /// Coordinator to report progress and matches to, when run as a worker of a distributed search
+    #[cfg(feature = "distributed")]
+    #[arg(skip)]
+    coordinator: Option<CoordinatorLink>,
+    /// Line of a `--patterns` file this search is for, to tag its matches with
+    #[arg(skip)]
+    source_pattern: Option<String>,
 }
 
-/// Derive authentication key bytes vector from a reference to a private key.
-fn auth_key_bytes_vec(private_key_ref: &SigningKey) -> Vec<u8> {
-    // Get public key from private key
-    let mut public = Into::<ed25519_dalek::VerifyingKey>::into(private_key_ref)
-        .to_bytes()
-        .to_vec();
-    // Push 0 which is the schema identifier for Ed25519 in the Aptos codebase
-    public.push(0);
-    // Hash the result and return the hash
-    let mut h = Sha3_256::new();
-    h.update(public);
-    h.finalize().to_vec()
-}
-
-/// Creates a multisig account address.
-/// Code inspired by the Aptos codebase.
-/// This is reimplemented to avoid adding Aptos as a dependency which adds over 200 other crates.
-/// See https://github.com/aptos-labs/aptos-core/blob/e2c8a6726a9bc4be464a755b47a113771a89e72c/types/src/account_address.rs#L239
-pub fn create_multisig_account_address(mut creator: Vec<u8>, creator_nonce: u64) -> Vec<u8> {
-    let mut full_seed = vec![];
-    full_seed.extend(b"aptos_framework::multisig_account");
-    full_seed.extend(creator_nonce.to_le_bytes());
-    creator.extend(full_seed);
-    creator.push(255);
-    let mut h = Sha3_256::new();
-    h.update(creator);
-    h.finalize().to_vec()
-}
-
-/// Parse command line arguments, verifying hex characters and specified thread count.
-fn parse_args() -> Result<CliArgs> {
-    let mut args = CliArgs::parse();
+/// Render an SDK snippet that loads the account for a found private key, given its formatted
+/// address.
+fn sdk_snippet(format: ExportFormat, address: &str, private_key: &str) -> String {
+    let lines = match format {
+        ExportFormat::TsSdk => vec![
+            "import { Account, Ed25519PrivateKey } from \"@aptos-labs/ts-sdk\";".to_string(),
+            String::new(),
+            format!("// Account address: {}", address),
+            "const account = Account.fromPrivateKey({".to_string(),
+            format!(
+                "  privateKey: new Ed25519PrivateKey(\"0x{}\"),",
+                private_key
+            ),
+            "});".to_string(),
+        ],
+        ExportFormat::PySdk => vec![
+            "from aptos_sdk.account import Account".to_string(),
+            String::new(),
+            format!("# Account address: {}", address),
+            format!("account = Account.load_key(\"0x{}\")", private_key),
+        ],
+    };
+    lines.join("\n")
+}
 
+/// Verify search arguments have valid hex characters, then normalize them to lowercase.
+fn validate_args(mut args: CliArgs) -> Result<CliArgs> {
+    warnings::set_format(args.warning_format);
     let r = Regex::new(r"^[0-9a-fA-F]+$").unwrap();
 
     // Verify prefix has valid hex characters.
-    if !args
-        .prefix
-        .clone()
-        .map_or(true, |prefix| r.is_match(&prefix))
-    {
+    if !args.prefix.as_ref().is_none_or(|prefix| r.is_match(prefix)) {
         bail!(
             "prefix '{}' is not a valid hex prefix",
             &args.prefix.unwrap()
         );
     }
 
-    // Verify prefix has valid hex characters.
-    if !args
-        .suffix
-        .clone()
-        .map_or(true, |suffix| r.is_match(&suffix))
-    {
+    // Verify suffix has valid hex characters.
+    if !args.suffix.as_ref().is_none_or(|suffix| r.is_match(suffix)) {
         bail!(
             "suffix '{}' is not a valid hex suffix",
             &args.suffix.unwrap()
         );
     }
 
-    args.prefix = args.prefix.map(|e| e.to_lowercase());
-    args.suffix = args.suffix.map(|e| e.to_lowercase());
+    // Verify creator patterns have valid hex characters.
+    for pattern in [&args.creator_prefix, &args.creator_suffix]
+        .into_iter()
+        .flatten()
+    {
+        if !r.is_match(pattern) {
+            bail!("creator pattern '{}' is not valid hex", pattern);
+        }
+    }
+
+    // Verify exact target has valid hex characters, then search for it as a prefix.
+    let exact = args.exact.take();
+    if let Some(exact) = &exact {
+        let target = exact.strip_prefix("0x").unwrap_or(exact);
+        if !r.is_match(target) {
+            bail!("exact target '{}' is not a valid hex address prefix", exact);
+        }
+        args.prefix = Some(target.to_string());
+    }
+
+    // Addresses are lowercase hex, so match patterns case-insensitively, but say so.
+    for (name, pattern) in [
+        ("prefix", &mut args.prefix),
+        ("suffix", &mut args.suffix),
+        ("creator prefix", &mut args.creator_prefix),
+        ("creator suffix", &mut args.creator_suffix),
+    ] {
+        let Some(pattern) = pattern else {
+            continue;
+        };
+        let lowercase = pattern.to_lowercase();
+        if lowercase != *pattern {
+            warnings::warn(
+                "pattern_lowercased",
+                format!(
+                    "{} '{}' contains uppercase characters, searching for '{}'",
+                    name, pattern, lowercase
+                ),
+            );
+            *pattern = lowercase;
+        }
+    }
+
+    // Confine the search to this machine's partition by extending the prefix.
+    if let Some(partition) = args.partition {
+        let mut prefix = args.prefix.take().unwrap_or_default();
+        prefix.push_str(&partition.prefix_characters());
+        args.prefix = Some(prefix).filter(|prefix| !prefix.is_empty());
+    }
+
+    // Verify multisig owners are valid addresses, and the threshold is achievable by the owners
+    // plus the creator.
+    for owner in args.multisig_owners.iter_mut() {
+        let address = owner.strip_prefix("0x").unwrap_or(owner);
+        if address.is_empty() || address.len() > ADDRESS_LENGTH_NIBBLES || !r.is_match(address) {
+            bail!("multisig owner '{}' is not a valid address", owner);
+        }
+        *owner = address.to_lowercase();
+    }
+    if let Some(threshold) = args.multisig_threshold {
+        let n_owners = args.multisig_owners.len() as u64 + 1;
+        if threshold == 0 || threshold > n_owners {
+            bail!(
+                "multisig threshold must be between 1 and the {} owners, including the creator",
+                n_owners
+            );
+        }
+    }
+
+    // Verify prefix and suffix can be satisfied together.
+    let prefix = args.prefix.as_deref().unwrap_or_default();
+    let suffix = args.suffix.as_deref().unwrap_or_default();
+    constraints::check(&[Constraint::prefix(prefix), Constraint::suffix(suffix)])?;
+    constraints::check(&[
+        Constraint::prefix(args.creator_prefix.as_deref().unwrap_or_default()),
+        Constraint::suffix(args.creator_suffix.as_deref().unwrap_or_default()),
+    ])
+    .context("creator patterns can't be matched")?;
+
+    if let Some(exact) = exact {
+        confirm_exact(&exact, prefix.len(), args.confirm_exact)?;
+    }
+
+    // Only one destination can own standard output.
+    if args
+        .output
+        .iter()
+        .filter(|output| matches!(output, OutputSpec::Console) || output.is_structured())
+        .count()
+        > 1
+    {
+        bail!("only one of console, json, and ndjson can be output to standard output at once");
+    }
+
+    // Redacting output must not lose the only copy of a private key.
+    if args.redact != Redaction::None && args.shamir.is_none() {
+        bail!("--redact requires --shamir, so that each private key is still kept, as shares");
+    }
+    if let Some(spec) = args.shamir {
+        if args.shamir_dir.len() > spec.shares as usize {
+            bail!(
+                "--shamir-dir was given {} directories for only {} shares",
+                args.shamir_dir.len(),
+                spec.shares
+            );
+        }
+    }
+
+    if args.lite {
+        args.threads = 1;
+        args.max_memory = args.max_memory.or(Some(LITE_MAX_MEMORY_MIB));
+    }
+
+    if let Some(simulation) = args.simulate {
+        warnings::warn(
+            "simulated_search",
+            format!(
+                "simulating a search at {:.0} it/s with a match every {:?}: no keys are searched, \
+                 and the keys output don't match the pattern",
+                simulation.rate, simulation.hit_after
+            ),
+        );
+    }
+
+    // Threads beyond the available cores only compete with each other.
+    let n_cores = default_threads();
+    if args.threads > n_cores {
+        warnings::warn(
+            "threads_exceed_cores",
+            format!(
+                "{} threads requested, but only {} cores are available, so threads will compete \
+                 for them",
+                args.threads, n_cores
+            ),
+        );
+    }
 
     Ok(args)
 }
 
-/// Return `true` if string slice reference indicates a string with an odd number of characters.
-fn has_odd_character_count(string_ref: &str) -> bool {
-    string_ref.len() % 2 == 1
+/// Require confirmation of an exact target, which is usually far too long to ever be found.
+///
+/// Confirmation is given interactively by typing `yes`, or up front with `--confirm-exact`.
+fn confirm_exact(exact: &str, n_characters: usize, confirmed: bool) -> Result<()> {
+    println!(
+        "Exact target {} fixes {} of the {} address characters, so only 1 in {} addresses match.",
+        exact,
+        n_characters,
+        ADDRESS_LENGTH_NIBBLES,
+        num::BigUint::from(16u8).pow(n_characters as u32)
+    );
+    if confirmed {
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() {
+        bail!("--exact needs interactive confirmation, or --confirm-exact");
+    }
+    print!("Type 'yes' to search anyway: ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if answer.trim() != "yes" {
+        bail!("exact target search not confirmed");
+    }
+    Ok(())
+}
+
+/// Parse a run label, which has to fit on one line of logs.
+fn parse_label(s: &str) -> Result<String> {
+    if s.chars().any(char::is_control) {
+        bail!("label must not contain control characters such as tabs or newlines");
+    }
+    Ok(s.to_string())
+}
+
+/// Generate a random (version 4) UUID identifying a run.
+fn run_uuid() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Escape a string for inclusion between quotes in JSON.
+fn json_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Parse a finite rate greater than 0.
+fn parse_rate(s: &str) -> Result<f64> {
+    let rate: f64 = s.parse()?;
+    if !(rate.is_finite() && rate > 0.0) {
+        bail!("rate must be a finite number greater than 0");
+    }
+    Ok(rate)
+}
+
+/// Parse a probability strictly between 0 and 1.
+fn parse_probability(s: &str) -> Result<f64> {
+    let probability: f64 = s.parse()?;
+    if !(probability > 0.0 && probability < 1.0) {
+        bail!("probability must be between 0 and 1");
+    }
+    Ok(probability)
+}
+
+/// How long to search on a single worker before starting the rest, measuring its throughput, so
+/// that patterns found almost instantly don't start a thread per core.
+const WARM_UP: Duration = Duration::from_secs(1);
+
+/// Expected search time beyond which to warn that the search is unlikely to ever finish.
+const LONG_SEARCH: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Interval between MQTT progress publishes.
+#[cfg(feature = "notifications")]
+const MQTT_PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Memory taken by a queued match, in bytes.
+const MATCH_BYTES: usize = std::mem::size_of::<Match>();
+
+/// Conservative upper bound on the memory taken by a queued pipeline item, in bytes.
+const PIPELINE_ITEM_BYTES: usize = 512;
+
+/// Memory reserved for everything but queued items under `--max-memory`, in mebibytes.
+const BASE_MEMORY_MIB: u64 = 16;
+
+/// Memory cap applied by `--lite`, in mebibytes.
+const LITE_MAX_MEMORY_MIB: u64 = 64;
+
+/// Sizes of the queues between threads, bounded so that queued items fit any memory cap.
+#[derive(Clone, Copy, Debug)]
+struct QueueSizes {
+    match_channel_capacity: usize,
+    match_batch_size: usize,
+    pipeline_depth: Option<usize>,
+}
+
+impl QueueSizes {
+    /// Size queues for a search, splitting any memory cap evenly between queued matches and
+    /// queued pipeline items.
+    fn new(args: &CliArgs, n_workers: usize) -> Result<Self> {
+        let pipeline_depth = args.pipeline_depth.filter(|_| args.multisig);
+        let Some(max_memory) = args.max_memory else {
+            return Ok(Self {
+                match_channel_capacity: MATCH_CHANNEL_CAPACITY,
+                match_batch_size: MATCH_BATCH_SIZE,
+                pipeline_depth,
+            });
+        };
+        if max_memory <= BASE_MEMORY_MIB {
+            bail!("--max-memory must be more than {} MiB", BASE_MEMORY_MIB);
+        }
+        let Some(budget) = (max_memory - BASE_MEMORY_MIB)
+            .checked_mul(1 << 20)
+            .and_then(|bytes| usize::try_from(bytes).ok())
+        else {
+            bail!(
+                "--max-memory of {} MiB is more than can be addressed",
+                max_memory
+            );
+        };
+        let budget = budget / 2;
+        // Matches queue in the channel, plus one pending batch per worker.
+        let match_batch_size = (budget / ((MATCH_CHANNEL_CAPACITY + n_workers) * MATCH_BYTES))
+            .clamp(1, MATCH_BATCH_SIZE);
+        let match_channel_capacity = (budget / (match_batch_size * MATCH_BYTES))
+            .saturating_sub(n_workers)
+            .clamp(1, MATCH_CHANNEL_CAPACITY);
+        let pipeline_depth = pipeline_depth
+            .map(|depth| depth.min((budget / (n_workers * PIPELINE_ITEM_BYTES)).max(1)));
+        Ok(Self {
+            match_channel_capacity,
+            match_batch_size,
+            pipeline_depth,
+        })
+    }
 }
 
-/// Convert a char to what hex number it represents.
-fn to_byte(c: char) -> u8 {
-    match c {
-        '0' => 0x0,
-        '1' => 0x1,
-        '2' => 0x2,
-        '3' => 0x3,
-        '4' => 0x4,
-        '5' => 0x5,
-        '6' => 0x6,
-        '7' => 0x7,
-        '8' => 0x8,
-        '9' => 0x9,
-        'a' => 0xa,
-        'b' => 0xb,
-        'c' => 0xc,
-        'd' => 0xd,
-        'e' => 0xe,
-        'f' => 0xf,
-        _ => panic!(),
+/// Number of search workers to run once warmed up.
+fn n_workers(args: &CliArgs) -> usize {
+    if args.multisig && args.pipeline_depth.is_some() {
+        // Each pipeline occupies two threads, one per SHA3 pass.
+        (args.threads / 2).max(1)
+    } else {
+        args.threads
     }
 }
 
-/// Generate a private key corresponding to a vanity prefix, while search is ongoing.
+/// Spawn a search worker thread, which only returns before the search is over if it fails.
 ///
-/// Once a match is found, a match message is transmitted to the main thread. Once the main thread
-/// has received sufficient match transactions, it will exit making all the other threads stop.
+/// For pipelined multisig searches the returned thread runs the hashing stage, and itself spawns
+/// the key generation stage, which exits as soon as the hashing stage does.
+fn spawn_worker(
+    args: &CliArgs,
+    sizes: QueueSizes,
+    match_tx: &SyncSender<Vec<Match>>,
+    counter: &Arc<AtomicU64>,
+    stop: &Arc<AtomicBool>,
+) -> JoinHandle<Result<()>> {
+    // Locally clone arguments not implementing copy trait so they can be moved into closure.
+    let matches = MatchBatch::new(match_tx.clone(), sizes.match_batch_size);
+    let prefix = args.prefix.clone();
+    let suffix = args.suffix.clone();
+    let creator_prefix = args.creator_prefix.clone();
+    let creator_suffix = args.creator_suffix.clone();
+    let counter = counter.clone();
+    let stop = stop.clone();
+    let scheme_byte = args.scheme_byte;
+    let reseed_policy = args.buffered_rng.then(|| ReseedPolicy {
+        keys: args.reseed_keys,
+        interval: Duration::from_secs(args.reseed_seconds),
+    });
+    // Assemble constant multisig seed material once for this worker.
+    let multisig_preimage = args
+        .multisig
+        .then(|| MultisigPreimage::new(&args.multisig_domain, SEQUENCE_NUMBER_MULTISIG));
+    if let Some(simulation) = args.simulate {
+        let n_workers = n_workers(args);
+        return thread::spawn(move || {
+            simulate::run_worker(
+                simulation,
+                n_workers,
+                scheme_byte,
+                multisig_preimage,
+                matches,
+                counter,
+                stop,
+            )
+        });
+    }
+    match (sizes.pipeline_depth, multisig_preimage) {
+        (Some(depth), Some(multisig_preimage)) => thread::spawn(move || {
+            let (auth_key_tx, auth_key_rx) = std::sync::mpsc::sync_channel(depth);
+            let (counter2, stop2) = (counter.clone(), stop.clone());
+            thread::spawn(move || {
+                generate_auth_keys(
+                    scheme_byte,
+                    Matcher::new(prefix_bytes(creator_prefix)?, suffix_bytes(creator_suffix)?),
+                    KeyRng::new(reseed_policy)?,
+                    auth_key_tx,
+                    counter2,
+                    stop2,
+                )
+            });
+            match_multisig_addresses(
+                prefix,
+                suffix,
+                multisig_preimage,
+                auth_key_rx,
+                matches,
+                counter,
+                stop,
+            )
+        }),
+        (_, multisig_preimage) => thread::spawn(move || {
+            generate_key(
+                prefix,
+                suffix,
+                Matcher::new(prefix_bytes(creator_prefix)?, suffix_bytes(creator_suffix)?),
+                scheme_byte,
+                multisig_preimage,
+                KeyRng::new(reseed_policy)?,
+                matches,
+                counter,
+                stop,
+            )
+        }),
+    }
+}
+
+/// Extract a human-readable message from a thread panic payload.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        format!("panicked: {}", message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        format!("panicked: {}", message)
+    } else {
+        "panicked".to_string()
+    }
+}
+
+/// Summary of a completed search.
+struct SearchSummary {
+    /// Number of vanity accounts found
+    found: u64,
+    /// Number of addresses generated
+    attempts: u64,
+    /// Wall-clock duration of the search
+    elapsed: Duration,
+}
+
+/// Threads of a running search, along with the receiving end of its match channel, stopped and
+/// joined when dropped so that a search ending early on an error doesn't leave its supervisor
+/// respawning workers behind it.
+struct SearchThreads {
+    stop: Arc<AtomicBool>,
+    match_rx: Option<Receiver<Vec<Match>>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl SearchThreads {
+    /// Receive the next batch of matches, or `None` once every worker has exited.
+    fn recv(&self) -> Option<Vec<Match>> {
+        self.match_rx.as_ref()?.recv().ok()
+    }
+
+    /// Stop every thread and wait for them to exit.
+    fn join(&mut self) {
+        self.stop.store(true, Relaxed);
+        // Workers blocked on a full channel fail once it's dropped, which is how they stop.
+        self.match_rx = None;
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for SearchThreads {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+/// Search with a progress spinner of its own.
+fn search(args: &CliArgs) -> Result<SearchSummary> {
+    search_with_progress(args, indicatif::ProgressBar::new_spinner())
+}
+
+/// Starts a timer, then spawns parallel search threads. Once search threads have transmitted back
+/// enough match messages, stops all search threads and prints elapsed time.
 ///
-/// # Arguments
+/// Progress is shown on the given bar, which concurrent searches each add to a shared display.
 ///
-/// * `prefix` - The vanity prefix to search against
-/// * `prefix` - The vanity suffix to search against
-/// * `multisig` - If `true` search for a multisig address
-/// * `match_tx` - Transmit channel for match message sent to main thread when a match is found
-/// * `counter` - Atomic integer that keeps track of the total number of addresses generated
-fn generate_key(
-    prefix: Option<String>,
-    suffix: Option<String>,
-    multisig: bool,
-    match_tx: std::sync::mpsc::Sender<(String, String, Option<String>)>,
-    counter: Arc<AtomicU64>,
-) -> Result<()> {
-    // Translate prefix string to bytes
-    let prefix = if let Some(s) = prefix {
-        Some(if has_odd_character_count(&s) {
-            let c = s.chars().last().unwrap();
-            (hex::decode(s[..s.len() - 1].to_string())?, Some(to_byte(c)))
-        } else {
-            (hex::decode(s.to_string())?, None)
-        })
+/// Offline searches run on a thread of their own, which along with the threads it spawns is
+/// forbidden from creating network sockets, leaving the rest of the process unaffected.
+fn search_with_progress(args: &CliArgs, bar: indicatif::ProgressBar) -> Result<SearchSummary> {
+    if !args.offline {
+        return run_search(args, bar);
+    }
+    thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                offline::forbid_network_sockets()?;
+                run_search(args, bar)
+            })
+            .join()
+            .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+    })
+}
+
+/// Run a search, showing progress on the given bar.
+fn run_search(args: &CliArgs, bar: indicatif::ProgressBar) -> Result<SearchSummary> {
+    let start_time = Instant::now();
+    // Keep standard output clean for JSON output.
+    let plain_text = !args.output.iter().any(OutputSpec::is_structured);
+    let bar = if args.first_match_fast || !plain_text {
+        indicatif::ProgressBar::hidden()
     } else {
-        None
+        bar
     };
+    if args.offline {
+        offline::assert_no_network_sockets("before the search")?;
+    }
 
-    // Translate suffix string to bytes
-    let suffix = if let Some(s) = suffix {
-        Some(if has_odd_character_count(&s) {
-            let c = s.chars().next().unwrap();
-            (hex::decode(s[1..].to_string())?, Some(to_byte(c)))
+    // Show progress toward an attempt budget, rather than an indefinite spinner.
+    if let Some(max_attempts) = args.max_attempts {
+        let prefix = if bar.prefix().is_empty() {
+            ""
         } else {
-            (hex::decode(s.to_string())?, None)
+            "{prefix} "
+        };
+        bar.set_style(
+            indicatif::ProgressStyle::with_template(&format!(
+                "{}{{spinner}} {{percent:>3}}% [{{bar:30}}] ETA {{eta}} | {{msg}}",
+                prefix
+            ))
+            .unwrap()
+            .progress_chars("=> "),
+        );
+        bar.set_length(max_attempts);
+    }
+
+    let n_workers = n_workers(args);
+    let sizes = QueueSizes::new(args, n_workers)?;
+
+    // Initialize message channels for match and exit messages.
+    let (match_tx, match_rx) = std::sync::mpsc::sync_channel(sizes.match_channel_capacity);
+
+    let count = Arc::new(AtomicU64::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+
+    // Load attempts made against the same pattern in prior runs.
+    let attempts_file = match &args.attempts_file {
+        Some(path) => Some(Arc::new(AttemptsFile::load(path, search_key(args))?)),
+        None => None,
+    };
+    let prior_attempts = attempts_file.as_ref().map_or(0, |file| file.prior());
+
+    let audit_log = args.audit_log.as_deref().map(AuditLog::open).transpose()?;
+    let mut dedupe_db = args.dedupe_db.as_deref().map(DedupeDb::open).transpose()?;
+    #[cfg(feature = "notifications")]
+    let mqtt = match args.mqtt.as_deref() {
+        Some([broker, topic]) => Some(Arc::new(Mutex::new(MqttPublisher::connect(broker, topic)?))),
+        _ => None,
+    };
+    let run_id = run_uuid();
+    let label = args.label.clone().unwrap_or_default();
+    // Run identification included in every telemetry payload.
+    let mut run_tags = format!(
+        "\"run\":\"{}\",\"label\":\"{}\"",
+        run_id,
+        json_escape(&label)
+    );
+    if let Some(pattern) = &args.source_pattern {
+        run_tags.push_str(&format!(",\"pattern\":\"{}\"", json_escape(pattern)));
+    }
+    if let Some(label) = args.label.as_ref().filter(|_| plain_text) {
+        println!("Run: {} ({})", label, run_id);
+        println!();
+    }
+    if let Some(partition) = args.partition.filter(|_| plain_text) {
+        println!(
+            "Partition {} of {}: searching for addresses starting {}",
+            partition.index,
+            partition.count,
+            args.prefix.as_deref().unwrap_or_default()
+        );
+        println!();
+    }
+    let mut operator_key = args
+        .operator_key
+        .as_deref()
+        .map(OperatorKey::load)
+        .transpose()?;
+    if let Some(operator_key) = operator_key.as_ref().filter(|_| plain_text) {
+        println!("Operator public key: 0x{}", operator_key.public_key());
+        println!();
+    }
+    let manifest = args
+        .manifest
+        .as_deref()
+        .map(|path| Manifest::create(path, args, &run_id, &label))
+        .transpose()?;
+
+    let match_json = MatchJson::new(args, &run_tags);
+    let mut sinks: Vec<Box<dyn OutputSink>> = vec![];
+    if let Some(audit_log) = audit_log {
+        let pattern = pattern_key(
+            args.multisig,
+            args.prefix.as_deref(),
+            args.suffix.as_deref(),
+        );
+        sinks.push(Box::new(AuditSink::new(
+            audit_log, &run_id, &label, pattern,
+        )));
+    }
+    #[cfg(feature = "notifications")]
+    let digest = args.mqtt_digest_minutes.map(|minutes| {
+        Arc::new(Mutex::new(MatchDigest::new(
+            Duration::from_secs(minutes * 60),
+            &run_tags,
+        )))
+    });
+    // Deliver matches to the broker from their own thread, so a slow broker can't stall the search.
+    #[cfg(feature = "notifications")]
+    let delivery = mqtt
+        .as_ref()
+        .map(|mqtt| Arc::new(Delivery::spawn(mqtt.clone(), &bar)));
+    #[cfg(feature = "notifications")]
+    if let Some(delivery) = &delivery {
+        sinks.push(Box::new(MqttSink::new(delivery.clone(), digest.clone())));
+    }
+    for output in &args.output {
+        sinks.push(match output {
+            OutputSpec::Console => Box::new(ConsoleSink::new(args, &run_id, &bar)),
+            OutputSpec::Json => Box::new(JsonSink::new(false)),
+            OutputSpec::Ndjson => Box::new(JsonSink::new(true)),
+            OutputSpec::File(path) => Box::new(FileSink::open(path.clone())?),
+        });
+    }
+    #[cfg(feature = "distributed")]
+    if let Some(coordinator) = &args.coordinator {
+        sinks.push(Box::new(CoordinatorSink::new(coordinator.clone(), &bar)));
+    }
+    // Record matches in the dedupe database and session last, so a match is only recorded once it
+    // has been output.
+    if let Some(dedupe_db) = &dedupe_db {
+        sinks.push(Box::new(dedupe_db.recorder()?));
+    }
+    if let Some((path, job)) = &args.session {
+        sinks.push(Box::new(SessionLog::open(path, job)?));
+    }
+    if args.shuffle_output {
+        // Sign shuffled matches as they are output rather than as they are found.
+        let signer = operator_key.take().map(|key| (key, run_id.clone()));
+        sinks = vec![Box::new(ShuffleSink::new(
+            sinks,
+            match_json.clone(),
+            signer,
+        ))];
+    }
+
+    let found = Arc::new(AtomicU64::new(0));
+    let n_guesses_needed = args.count;
+    let pattern_length = constraints::n_fixed_characters(&[
+        Constraint::prefix(args.prefix.as_deref().unwrap_or_default()),
+        Constraint::suffix(args.suffix.as_deref().unwrap_or_default()),
+    ]) + constraints::n_fixed_characters(&[
+        Constraint::prefix(args.creator_prefix.as_deref().unwrap_or_default()),
+        Constraint::suffix(args.creator_suffix.as_deref().unwrap_or_default()),
+    ]);
+    // Chance of getting the right address each time a guess is made
+    let chance = 16f64.powi(-(pattern_length as i32));
+
+    // Spawn a single search thread to warm up on, then supervise search threads, starting the rest
+    // once warmed up and respawning any that die.
+    let respawns = Arc::new(AtomicU64::new(0));
+    let ramped_up = Arc::new(AtomicBool::new(n_workers == 1 || args.first_match_fast));
+    let mut workers = vec![spawn_worker(args, sizes, &match_tx, &count, &stop)];
+    let (args2, bar2, count2, found2, ramped_up2, respawns2, stop2) = (
+        args.clone(),
+        bar.clone(),
+        count.clone(),
+        found.clone(),
+        ramped_up.clone(),
+        respawns.clone(),
+        stop.clone(),
+    );
+    let supervisor = if args.first_match_fast {
+        // Start every worker at once, unsupervised, for the fastest first match.
+        workers.extend((1..n_workers).map(|_| spawn_worker(args, sizes, &match_tx, &count, &stop)));
+        drop(match_tx);
+        thread::spawn(move || {
+            for worker in workers {
+                let _ = worker.join();
+            }
         })
     } else {
-        None
+        thread::spawn(move || {
+            let warm_up_start = Instant::now();
+            let mut estimated = false;
+            while !stop2.load(Relaxed) {
+                thread::sleep(Duration::from_millis(100));
+                // Warn once warmed up if even all the workers are expected to take too long.
+                let warm_up_elapsed = warm_up_start.elapsed();
+                if !estimated && warm_up_elapsed >= WARM_UP {
+                    estimated = true;
+                    let rate = count2.load(Relaxed) as f64 / warm_up_elapsed.as_secs_f64();
+                    let n_remaining = n_guesses_needed.saturating_sub(found2.load(Relaxed));
+                    let expected = n_remaining as f64 / chance / rate / n_workers as f64;
+                    if expected > LONG_SEARCH.as_secs_f64() {
+                        bar2.suspend(|| {
+                            warnings::warn(
+                                "long_search",
+                                format!(
+                                    "search is expected to take {} across {} workers",
+                                    estimate::format_seconds(expected),
+                                    n_workers
+                                ),
+                            )
+                        });
+                    }
+                }
+                // Start the rest of the workers once warmed up, unless the single worker is expected to
+                // find the remaining matches within the warm-up time anyway.
+                if !ramped_up2.load(Relaxed) && warm_up_elapsed >= WARM_UP {
+                    let rate = count2.load(Relaxed) as f64 / warm_up_elapsed.as_secs_f64();
+                    let n_remaining = n_guesses_needed.saturating_sub(found2.load(Relaxed));
+                    if n_remaining as f64 / chance / rate > WARM_UP.as_secs_f64() {
+                        workers.extend(
+                            (1..n_workers)
+                                .map(|_| spawn_worker(&args2, sizes, &match_tx, &count2, &stop2)),
+                        );
+                        ramped_up2.store(true, Relaxed);
+                    }
+                }
+                for worker in workers.iter_mut() {
+                    if !worker.is_finished() || stop2.load(Relaxed) {
+                        continue;
+                    }
+                    let dead = std::mem::replace(
+                        worker,
+                        spawn_worker(&args2, sizes, &match_tx, &count2, &stop2),
+                    );
+                    let cause = match dead.join() {
+                        Ok(Ok(())) => "exited unexpectedly".to_string(),
+                        Ok(Err(error)) => format!("{:#}", error),
+                        Err(payload) => panic_message(payload.as_ref()),
+                    };
+                    respawns2.fetch_add(1, Relaxed);
+                    bar2.suspend(|| {
+                        warnings::warn(
+                            "worker_respawned",
+                            format!("search worker died ({}), respawned", cause),
+                        )
+                    });
+                }
+            }
+            // Wait for workers to notice the search is over.
+            drop(match_tx);
+            for worker in workers {
+                let _ = worker.join();
+            }
+        })
     };
 
-    // Randomly generate private keys in a loop and check match against prefix bytes.
-    let mut rng = rand::rngs::OsRng;
-    loop {
-        // Generate a private key and from it, bytes to compare against prefix bytes.
-        let private_key = SigningKey::generate(&mut rng);
-        let account_address_bytes = auth_key_bytes_vec(&private_key);
-        let search_bytes = if multisig {
-            create_multisig_account_address(account_address_bytes, SEQUENCE_NUMBER_MULTISIG)
-        } else {
-            account_address_bytes
-        };
+    if prior_attempts > 0 && plain_text {
+        println!("Prior attempts on this pattern: {}", prior_attempts);
+        println!();
+    }
 
-        // Increment generated addresses counter
-        counter.fetch_add(1, Relaxed);
+    let mut stats = Stats::new(count.clone(), found.clone());
 
-        // Check prefix match
-        if let Some((pb, pc)) = &prefix {
-            if !search_bytes.starts_with(pb) {
-                continue;
-            }
-            if let Some(pc) = pc {
-                if !(search_bytes[pb.len()] >> 4 == *pc) {
+    // Publish progress telemetry every so often from its own thread, so a slow broker can't stall
+    // the progress display.
+    #[cfg(feature = "notifications")]
+    let telemetry = mqtt.clone().map(|mqtt| {
+        let samples = stats.subscribe();
+        let bar2 = bar.clone();
+        let run_tags = run_tags.clone();
+        let digest = digest.clone();
+        let delivery = delivery.clone();
+        thread::spawn(move || {
+            let mut next_publish = MQTT_PROGRESS_INTERVAL;
+            for sample in samples {
+                // Deliver held matches once due, even if no more arrive.
+                if let Some(payload) = digest.as_ref().and_then(|d| d.lock().unwrap().take(false)) {
+                    if let Some(delivery) = &delivery {
+                        delivery.send("digest", payload);
+                    }
+                }
+                if sample.elapsed < next_publish {
                     continue;
                 }
+                next_publish += MQTT_PROGRESS_INTERVAL;
+                let payload = format!(
+                    "{{{},\"attempts\":{},\"rate\":{:.0},\"found\":{},\"count\":{}}}",
+                    run_tags, sample.attempts, sample.smoothed_rate, sample.found, n_guesses_needed
+                );
+                if let Err(error) = mqtt.lock().unwrap().publish("progress", &payload) {
+                    bar2.suspend(|| warnings::warn("mqtt_publish_failed", format!("{:#}", error)));
+                }
             }
-        }
-        // Check suffix match
-        if let Some((sb, sc)) = &suffix {
-            if !search_bytes.ends_with(sb) {
+        })
+    });
+
+    #[cfg(not(feature = "notifications"))]
+    let telemetry = None;
+
+    let bar2 = bar.clone();
+    let attempts_file2 = attempts_file.clone();
+    let stop2 = stop.clone();
+    let offline = args.offline;
+    let offline_violation = Arc::new(Mutex::new(None));
+    let offline_violation2 = offline_violation.clone();
+    let until_probability = args.until_probability;
+    let until_probability_reached = Arc::new(AtomicBool::new(false));
+    let until_probability_reached2 = until_probability_reached.clone();
+    #[cfg(feature = "distributed")]
+    let coordinator = args.coordinator.clone();
+    let coordinator_stopped = Arc::new(AtomicBool::new(false));
+    #[cfg(feature = "distributed")]
+    let coordinator_stopped2 = coordinator_stopped.clone();
+    let max_attempts = args.max_attempts;
+    let max_attempts_reached = Arc::new(AtomicBool::new(false));
+    let max_attempts_reached2 = max_attempts_reached.clone();
+    let mut monitor = Some(ThroughputMonitor::new(args.throughput_alert_drop))
+        .filter(|_| args.throughput_alert_drop > 0);
+    let mut was_ramped_up = ramped_up.load(Relaxed);
+    #[cfg(feature = "notifications")]
+    let undelivered = delivery.as_ref().map(|delivery| delivery.pending());
+    // Skip sampling entirely for the fastest first match, since it sleeps between samples.
+    let stats = (!args.first_match_fast).then(|| {
+        thread::spawn(move || {
+            for tick in 1u64.. {
+                thread::sleep(Duration::from_millis(100));
+                if stop2.load(Relaxed) {
+                    break;
+                }
+                // The rate jumps once all workers have started, so smooth it afresh from then on.
+                let is_ramped_up = ramped_up.load(Relaxed);
+                if is_ramped_up && !was_ramped_up {
+                    stats.reset_smoothing();
+                    was_ramped_up = true;
+                }
+                let sample = stats.sample();
+
+                // Check for network sockets every second, ending the search if one is found.
+                if offline && tick % 10 == 0 {
+                    if let Err(error) = offline::assert_no_network_sockets("during the search") {
+                        *offline_violation2.lock().unwrap() = Some(error);
+                        stop2.store(true, Relaxed);
+                        break;
+                    }
+                }
+
+                // Give up once the requested matches should very probably have appeared by now.
+                if until_probability.is_some_and(|probability| {
+                    forecast::probability_found(
+                        n_guesses_needed,
+                        chance,
+                        prior_attempts + sample.attempts,
+                    ) >= probability
+                }) {
+                    until_probability_reached2.store(true, Relaxed);
+                    stop2.store(true, Relaxed);
+                    break;
+                }
+
+                // Report progress to the coordinator every second, and stop once it says so.
+                #[cfg(feature = "distributed")]
+                if let Some(coordinator) = &coordinator {
+                    if coordinator.is_stopped() {
+                        coordinator_stopped2.store(true, Relaxed);
+                        stop2.store(true, Relaxed);
+                        break;
+                    }
+                    if tick % 10 == 0 {
+                        if let Err(error) =
+                            coordinator.report_progress(sample.attempts, sample.smoothed_rate)
+                        {
+                            bar2.suspend(|| {
+                                warnings::warn("coordinator_unreachable", format!("{:#}", error))
+                            });
+                        }
+                    }
+                }
+
+                // Give up once the attempt budget is spent.
+                if max_attempts.is_some_and(|max_attempts| sample.attempts >= max_attempts) {
+                    max_attempts_reached2.store(true, Relaxed);
+                    stop2.store(true, Relaxed);
+                    break;
+                }
+                bar2.set_position(sample.attempts);
+
+                // Periodically persist attempts so an interrupted run still records its effort.
+                if let Some(file) = attempts_file2.as_ref().filter(|_| tick % 100 == 0) {
+                    if let Err(error) = file.save(sample.attempts) {
+                        bar2.suspend(|| {
+                            warnings::warn("attempts_not_saved", format!("{:#}", error))
+                        });
+                    }
+                }
+
+                let mut message = format!("Iterations per second: {:.0} it/s", sample.rate);
+
+                // Chance that the requested matches should have appeared by now, given the effort,
+                // including prior runs'.
+                message.push_str(&format!(
+                    " | {:.0}% chance of {} by now",
+                    forecast::probability_found(
+                        n_guesses_needed,
+                        chance,
+                        prior_attempts + sample.attempts
+                    ) * 100.0,
+                    if n_guesses_needed == 1 {
+                        "a match".to_string()
+                    } else {
+                        format!("{} matches", n_guesses_needed)
+                    }
+                ));
+
+                // Forecast when the remaining matches will have been found, at the smoothed rate, once
+                // all workers have started.
+                if !is_ramped_up {
+                    message.push_str(" | warming up on one thread");
+                } else if sample.smoothed_rate > 0.0 {
+                    let n_remaining = n_guesses_needed.saturating_sub(sample.found);
+                    let forecast = |probability| {
+                        let attempts =
+                            forecast::attempts_quantile(n_remaining, chance, probability);
+                        forecast::local_time_after(attempts / sample.smoothed_rate)
+                    };
+                    message.push_str(&format!(
+                        " | 50% chance done by {}, 95% by {}",
+                        forecast(0.5),
+                        forecast(0.95)
+                    ));
+                }
+
+                // Show matches still waiting on a slow broker, so a growing backlog is visible.
+                #[cfg(feature = "notifications")]
+                if let Some(n_undelivered) = undelivered
+                    .as_ref()
+                    .map(|n| n.load(Relaxed))
+                    .filter(|&n| n > 0)
+                {
+                    message.push_str(&format!(" | {} awaiting MQTT delivery", n_undelivered));
+                }
+
+                // Watch for sustained throughput drops, once the smoothed rate has settled.
+                if let Some(monitor) = monitor.as_mut().filter(|_| sample.warmed_up) {
+                    match monitor.observe(sample.smoothed_rate) {
+                        Some(ThroughputAlert::Dropped { rate, baseline }) => bar2.suspend(|| {
+                            warnings::warn(
+                                "throughput_dropped",
+                                format!(
+                                "throughput has dropped to {:.0} it/s, {:.0}% below its baseline \
+                                 of {:.0} it/s. Check for thermal throttling, dead workers, or \
+                                 other processes competing for CPU.",
+                                rate,
+                                monitor.drop_percent(rate),
+                                baseline
+                            ),
+                            )
+                        }),
+                        Some(ThroughputAlert::Recovered { rate }) => bar2
+                            .suspend(|| eprintln!("Throughput has recovered to {:.0} it/s.", rate)),
+                        None => {}
+                    }
+                    if monitor.is_low() {
+                        message.push_str(&format!(
+                            " (warning: throughput {:.0}% below baseline)",
+                            monitor.drop_percent(sample.smoothed_rate)
+                        ));
+                    }
+                }
+
+                match respawns.load(Relaxed) {
+                    0 => {}
+                    n => message.push_str(&format!(" (warning: {} worker respawns)", n)),
+                }
+                bar2.set_message(message);
+                bar2.tick();
+            }
+        })
+    });
+
+    let mut threads = SearchThreads {
+        stop: stop.clone(),
+        match_rx: Some(match_rx),
+        handles: [Some(supervisor), stats, telemetry]
+            .into_iter()
+            .flatten()
+            .collect(),
+    };
+
+    // Stop search after the desired number of addresses have been generated.
+    let mut n_found = 0;
+    let (mut previous_attempts, mut previous_elapsed) = (0, Duration::ZERO);
+    let mut sampler = OutputSampler::new(args.sample, args.max_results_per_second);
+    let mut n_filtered = 0;
+    while n_found < args.count {
+        let Some(batch) = threads.recv() else { break };
+        // For a freshly generated standard account, the address is the authentication key.
+        for found_match in batch {
+            if n_found >= args.count {
+                break;
+            }
+            let auth_key = hex::encode(found_match.auth_key);
+            let multi = match found_match.kind {
+                AccountKind::Standard => None,
+                AccountKind::Multisig { address } => Some(hex::encode(address)),
+            };
+            let address = multi.as_ref().unwrap_or(&auth_key);
+            if dedupe_db
+                .as_mut()
+                .is_some_and(|db| db.is_duplicate(address))
+                || !sampler.keep()
+            {
                 continue;
             }
-            if let Some(sc) = sc {
-                if !(search_bytes[search_bytes.len() - sb.len() - 1] & 0x0f == *sc) {
-                    continue;
-                }
+            let public = hex::encode(found_match.public_key);
+            let pk = hex::encode(found_match.private_key);
+            // Effort spent on this match, since the previous match and since the run started.
+            let (attempts, elapsed) = (count.load(Relaxed), start_time.elapsed());
+            let (match_attempts, match_elapsed) =
+                (attempts - previous_attempts, elapsed - previous_elapsed);
+            (previous_attempts, previous_elapsed) = (attempts, elapsed);
+            let commitment = args
+                .commit_key
+                .as_ref()
+                .map(|key| hex::encode(key.commit(&found_match.private_key)));
+            let signature = operator_key.as_ref().map(|key| key.sign(address, &run_id));
+            let partially_redacted = format!("{}...{}", &pk[..4], &pk[pk.len() - 4..]);
+            let mut record = MatchRecord {
+                address,
+                auth_key: &auth_key,
+                public_key: &public,
+                private_key: match args.redact {
+                    Redaction::None => args.shamir.is_none().then_some(pk.as_str()),
+                    Redaction::Partial => Some(&partially_redacted),
+                    Redaction::Full => None,
+                },
+                redacted: args.redact != Redaction::None,
+                commitment: commitment.as_deref(),
+                signature: signature.as_ref(),
+                share_paths: &[],
+                multisig: multi.is_some(),
+                match_attempts,
+                match_elapsed,
+                attempts,
+                elapsed,
+                json: "",
+            };
+            if !args.filter.iter().all(|filter| filter.passes(&record)) {
+                n_filtered += 1;
+                continue;
+            }
+            n_found += 1;
+            found.store(n_found, Relaxed);
+            if let Some(dedupe_db) = &mut dedupe_db {
+                dedupe_db.claim(address);
+            }
+            let share_paths = match args.shamir {
+                Some(spec) => shamir::write_shares(
+                    &args.shamir_dir,
+                    &auth_key,
+                    &found_match.private_key,
+                    spec,
+                )?,
+                None => vec![],
+            };
+            record.share_paths = &share_paths;
+            let json = match_json.members(&record);
+            record.json = &json;
+            for sink in sinks.iter_mut() {
+                sink.emit(&record)?;
             }
         }
+    }
 
-        // Send match
-        let str = hex::encode(search_bytes);
-        let pk = hex::encode(private_key.to_bytes());
-        if multisig {
-            match_tx.send((hex::encode(auth_key_bytes_vec(&private_key)), pk, Some(str)))?;
-        } else {
-            match_tx.send((str, pk, None))?;
+    // Stop all search threads, and make sure they have exited before the next search starts.
+    threads.join();
+    bar.finish_and_clear();
+    let summary = SearchSummary {
+        found: n_found,
+        attempts: count.load(Relaxed),
+        elapsed: start_time.elapsed(),
+    };
+    for sink in sinks.iter_mut() {
+        sink.finish(summary.attempts, summary.elapsed)?;
+    }
+    if let Some(error) = offline_violation.lock().unwrap().take() {
+        return Err(error);
+    }
+    if args.offline {
+        offline::assert_no_network_sockets("after the search")?;
+    }
+    if max_attempts_reached.load(Relaxed) && plain_text {
+        println!(
+            "Stopped with {} of {} found, after the maximum of {} attempts",
+            n_found,
+            args.count,
+            args.max_attempts.unwrap_or_default()
+        );
+    }
+    if until_probability_reached.load(Relaxed) && plain_text {
+        println!(
+            "Stopped with {} of {} found, once there was a {:.1}% chance of having found all of them",
+            n_found,
+            args.count,
+            args.until_probability.unwrap_or_default() * 100.0
+        );
+    }
+    if coordinator_stopped.load(Relaxed) && plain_text {
+        println!(
+            "Stopped with {} of {} found, once the coordinator's search was over",
+            n_found, args.count
+        );
+    }
+
+    #[cfg(feature = "notifications")]
+    if let (Some(mqtt), Some(delivery)) = (&mqtt, &delivery) {
+        // Deliver any matches still queued or held for a digest before announcing completion.
+        if let Some(payload) = digest.as_ref().and_then(|d| d.lock().unwrap().take(true)) {
+            delivery.send("digest", payload);
+        }
+        let n_undelivered = delivery.finish();
+        if n_undelivered > 0 {
+            warnings::warn(
+                "mqtt_undelivered",
+                format!(
+                    "gave up delivering {} matches to the MQTT broker",
+                    n_undelivered
+                ),
+            );
+        }
+        let payload = format!(
+            "{{{},\"found\":{},\"attempts\":{},\"elapsed_ms\":{}}}",
+            run_tags,
+            summary.found,
+            summary.attempts,
+            summary.elapsed.as_millis()
+        );
+        if let Err(error) = mqtt.lock().unwrap().publish_acknowledged("done", &payload) {
+            warnings::warn("mqtt_publish_failed", format!("{:#}", error));
         }
     }
+    if plain_text {
+        if let Some(label) = &args.label {
+            println!("Run: {} ({})", label, run_id);
+        }
+        println!("Elapsed time: {:#?}", summary.elapsed);
+        println!("Total addresses generated: {}", summary.attempts);
+        if let Some(dedupe_db) = dedupe_db.as_ref().filter(|db| db.duplicates > 0) {
+            println!("Duplicate matches skipped: {}", dedupe_db.duplicates);
+        }
+        if n_filtered > 0 {
+            println!("Matches skipped by filters: {}", n_filtered);
+        }
+        if sampler.skipped > 0 {
+            println!("Matches skipped by sampling: {}", sampler.skipped);
+        }
+    }
+    if let Some(file) = attempts_file {
+        file.save(summary.attempts)?;
+        if plain_text {
+            println!(
+                "Total attempts on this pattern: {}",
+                prior_attempts + summary.attempts
+            );
+        }
+    }
+    if let Some(manifest) = manifest {
+        manifest.finish(&summary)?;
+    }
+    Ok(summary)
 }
 
-/// Parses arguments, starts a timer, then spawns parallel search threads. Once search threads have
-/// transmitted back enough match messages, broadcasts an exit transaction and prints elapsed time.
-fn main() -> Result<()> {
-    let args = parse_args()?;
+/// Search for each pattern of a `--patterns` file, or standard input, one after another as they're
+/// read, printing a summary after each and a roll-up of all of them at the end.
+fn search_patterns(args: CliArgs) -> Result<()> {
+    let path = args.patterns.clone().unwrap();
+    let reader: Box<dyn BufRead> = if path == Path::new("-") {
+        Box::new(std::io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(&path).with_context(|| {
+            format!("reading patterns file {}", path.display())
+        })?))
+    };
+
     let start_time = Instant::now();
+    let plain_text = !args.output.iter().any(OutputSpec::is_structured);
+    let (mut n_patterns, mut n_skipped, mut n_found, mut n_attempts) = (0, 0, 0, 0);
+    for line in reader.lines() {
+        let line = line.context("reading patterns")?;
+        let pattern = line.trim();
+        if pattern.is_empty() {
+            continue;
+        }
+        let (prefix, suffix) = pattern.split_once("..").unwrap_or((pattern, ""));
+        let mut pattern_args = args.clone();
+        pattern_args.patterns = None;
+        pattern_args.prefix = Some(prefix.to_string()).filter(|prefix| !prefix.is_empty());
+        pattern_args.suffix = Some(suffix.to_string()).filter(|suffix| !suffix.is_empty());
+        pattern_args.source_pattern = Some(pattern.to_string());
+        // Skip invalid lines rather than abandoning the rest of a long list over one typo.
+        let pattern_args = match validate_args(pattern_args) {
+            Ok(pattern_args) => pattern_args,
+            Err(error) => {
+                warnings::warn(
+                    "invalid_pattern",
+                    format!("skipping invalid pattern '{}': {:#}", pattern, error),
+                );
+                n_skipped += 1;
+                continue;
+            }
+        };
+        n_patterns += 1;
+        if plain_text {
+            println!("=== Pattern {}: {} ===", n_patterns, pattern);
+            println!();
+        }
+        let summary = search(&pattern_args)?;
+        if plain_text {
+            println!(
+                "Pattern {} finished: {} of {} found, {} addresses generated in {:#?}",
+                pattern, summary.found, args.count, summary.attempts, summary.elapsed
+            );
+            println!();
+        }
+        n_found += summary.found;
+        n_attempts += summary.attempts;
+    }
+    if n_patterns == 0 {
+        bail!("no valid patterns given");
+    }
+    if !plain_text {
+        return Ok(());
+    }
+    println!(
+        "Total: {} patterns, {} found, {} addresses generated in {:#?}",
+        n_patterns,
+        n_found,
+        n_attempts,
+        start_time.elapsed()
+    );
+    if n_skipped > 0 {
+        println!("Invalid patterns skipped: {}", n_skipped);
+    }
+    Ok(())
+}
 
-    // Initialize message channels for match and exit messages.
-    let (match_tx, match_rx) = std::sync::mpsc::channel::<(String, String, Option<String>)>();
+/// Parse a job's search options, ignoring the `OPTIVANITY_*` environment variables, so that each
+/// job runs exactly as its jobs file says whatever environment it's run from.
+fn parse_job_args(argv: &[String]) -> Result<CliArgs> {
+    let command = CliArgs::command().mut_args(|arg| arg.env(None::<&'static str>));
+    Ok(CliArgs::from_arg_matches(
+        &command.try_get_matches_from(argv)?,
+    )?)
+}
 
-    let count = Arc::new(AtomicU64::new(0));
+/// Run each job in a jobs file, printing a summary after each and a roll-up of all of them at the
+/// end.
+///
+/// Jobs run one after another, or all at once if given a number of threads to split between them
+/// in proportion to their shares.
+fn run_jobs(
+    path: &Path,
+    concurrent_threads: Option<usize>,
+    session_path: Option<&Path>,
+) -> Result<()> {
+    let mut jobs = jobs::parse_jobs_file(path)?
+        .into_iter()
+        .map(|job| -> Result<(String, u64, CliArgs)> {
+            let args = parse_job_args(&job.argv())
+                .with_context(|| format!("invalid job '{}' in {}", job.name, path.display()))?;
+            if args.patterns.is_some() {
+                bail!(
+                    "job '{}' can't search --patterns, give each its own job",
+                    job.name
+                );
+            }
+            Ok((job.name, job.share, validate_args(args)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-    // Spawn parallel search threads.
-    for _ in 0..args.threads {
-        // Locally clone arguments not implementing copy trait so they can be moved into closure.
-        let match_tx = match_tx.clone();
-        let prefix = args.prefix.clone();
-        let suffix = args.suffix.clone();
-        let count = count.clone();
-        thread::spawn(move || {
-            let _ = generate_key(prefix, suffix, args.multisig, match_tx, count);
+    // Resume a session by searching only for the matches each job is still short of.
+    if let Some(session_path) = session_path {
+        if jobs
+            .iter()
+            .enumerate()
+            .any(|(i, (name, _, _))| jobs[..i].iter().any(|(other, _, _)| other == name))
+        {
+            bail!("job names must be unique to record a session");
+        }
+        let session = Session::load(session_path)?;
+        jobs.retain_mut(|(name, _, args)| {
+            let n_emitted = session.emitted(name).len() as u64;
+            if n_emitted >= args.count {
+                println!("Job {}: already found {} in the session", name, n_emitted);
+                return false;
+            }
+            if n_emitted > 0 {
+                println!(
+                    "Job {}: resuming, {} of {} already found in the session",
+                    name, n_emitted, args.count
+                );
+                args.count -= n_emitted;
+            }
+            args.session = Some((session_path.to_path_buf(), name.clone()));
+            true
         });
+        println!();
+        if jobs.is_empty() {
+            println!("All jobs in the session have already found their count");
+            return Ok(());
+        }
     }
 
-    let bar = indicatif::ProgressBar::new_spinner();
-
-    let bar2 = bar.clone();
-    let count2 = count.clone();
-    thread::spawn(move || {
-        // Chance of getting the right address each time a guess is made
-        let chance = BigInt::from_u8(16).unwrap();
-        let chance = chance
-            .pow((args.prefix.map_or(0, |e| e.len()) + args.suffix.map_or(0, |e| e.len())) as u32);
-
-        // Number of addresses to generate
-        let n_guesses_needed = args.count.clone();
-
-        let mut buf = vec![];
-        let mut first = true;
-        let mut prev_count = 0;
-
-        loop {
-            thread::sleep(Duration::from_millis(100));
-            let current_count = count2.load(Relaxed);
-            let it_per_s = (current_count - prev_count) * 10;
-            bar2.set_message(format!("Iterations per second: {} it/s", it_per_s));
-            bar2.tick();
-
-            // Store 5 it/s speeds, average that, then calculate the estimated amount of time
-            if buf.len() < 5 {
-                buf.push(it_per_s);
-            } else if first {
-                first = !first;
-                let average = BigInt::from_u64(buf.iter().sum::<u64>() / 5).unwrap();
-                let average_per_minute = average * 60;
-                let estimate: BigInt = chance.clone() * n_guesses_needed / average_per_minute;
-                bar2.suspend(|| {
-                    println!("Estimate: {} minutes", estimate);
-                    println!();
-                })
+    let start_time = Instant::now();
+    let summaries = match concurrent_threads {
+        Some(threads) => {
+            let total_share: u64 = jobs.iter().map(|(_, share, _)| share).sum();
+            for (name, share, args) in jobs.iter_mut() {
+                let job_threads = (threads as f64 * *share as f64 / total_share as f64).round();
+                args.threads = (job_threads as usize).max(1);
+                // Label matches with their job, since jobs' output is interleaved.
+                args.label.get_or_insert_with(|| name.clone());
+                println!("Job {}: {} threads", name, args.threads);
             }
-
-            prev_count = current_count;
+            println!();
+            run_jobs_concurrently(&jobs)?
         }
-    });
-
-    // Stop search after the desired number of addresses have been generated.
-    for _ in 0..args.count {
-        if let Ok((addr, pk, multi)) = match_rx.recv() {
-            bar.suspend(|| {
-                if let Some(multi) = multi {
-                    println!("Multisig account address: 0x{}", multi);
-                    println!("Standard account address: 0x{}", addr);
-                    println!("Private key:              0x{}", pk);
-                    println!();
-                } else {
-                    println!("Standard account address: 0x{}", addr);
-                    println!("Private key:              0x{}", pk);
-                    println!();
-                }
-            });
+        None => {
+            let mut summaries = vec![];
+            for (i, (name, _, args)) in jobs.iter().enumerate() {
+                println!("=== Job {}/{}: {} ===", i + 1, jobs.len(), name);
+                println!();
+                let summary = search(args)?;
+                println!(
+                    "Job {} finished: {} of {} found, {} addresses generated in {:#?}",
+                    name, summary.found, args.count, summary.attempts, summary.elapsed
+                );
+                println!();
+                summaries.push(summary);
+            }
+            summaries
         }
+    };
+
+    println!("=== Summary ===");
+    for ((name, _, args), summary) in jobs.iter().zip(&summaries) {
+        println!(
+            "{}: {} of {} found, {} addresses generated in {:#?}",
+            name, summary.found, args.count, summary.attempts, summary.elapsed
+        );
     }
+    println!(
+        "Total: {} jobs, {} found, {} addresses generated in {:#?}",
+        jobs.len(),
+        summaries.iter().map(|summary| summary.found).sum::<u64>(),
+        summaries
+            .iter()
+            .map(|summary| summary.attempts)
+            .sum::<u64>(),
+        start_time.elapsed()
+    );
+    Ok(())
+}
 
-    bar.finish_and_clear();
+/// Run jobs at once, each on its own threads and with its own line in a shared progress display,
+/// returning their summaries in order once all have finished.
+fn run_jobs_concurrently(jobs: &[(String, u64, CliArgs)]) -> Result<Vec<SearchSummary>> {
+    let progress = indicatif::MultiProgress::new();
+    let style = indicatif::ProgressStyle::with_template("{prefix} {spinner} {msg}").unwrap();
+    thread::scope(|scope| {
+        let handles: Vec<_> = jobs
+            .iter()
+            .map(|(name, _, args)| {
+                let bar = progress.add(indicatif::ProgressBar::new_spinner());
+                bar.set_style(style.clone());
+                bar.set_prefix(format!("[{}]", name));
+                scope.spawn(move || {
+                    search_with_progress(args, bar).with_context(|| format!("job {} failed", name))
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
 
-    println!("Elapsed time: {:#?}", start_time.elapsed());
-    println!("Total addresses generated: {}", count.load(Relaxed));
+/// Recover a private key from Shamir share files, checking it against the account address the
+/// shares were written for, and print it along with that address.
+fn recover(paths: &[PathBuf], scheme_byte: u8) -> Result<()> {
+    let shares = paths
+        .iter()
+        .map(|path| shamir::read_share(path))
+        .collect::<Result<Vec<_>>>()?;
+    let threshold = shares[0].threshold;
+    if shares.iter().any(|share| share.threshold != threshold) {
+        bail!("shares were split with different thresholds");
+    }
+    let address = shares[0].address.clone();
+    if shares.iter().any(|share| share.address != address) {
+        bail!("shares are of different accounts' keys");
+    }
+    if shares.len() < threshold as usize {
+        bail!(
+            "{} shares are needed to recover the key, but only {} were given",
+            threshold,
+            shares.len()
+        );
+    }
+    let shares: Vec<_> = shares
+        .into_iter()
+        .map(|share| (share.x, share.bytes))
+        .collect();
+    let private_key_bytes: [u8; 32] = shamir::combine(&shares)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("recovered secret is not a 32-byte private key"))?;
+    let private_key = SigningKey::from_bytes(&private_key_bytes);
+    let recovered_address = hex::encode(auth_key_bytes_vec(&private_key, scheme_byte));
+    // A wrong, corrupted, or missing share still combines, into a different key.
+    if let Some(address) = address {
+        if address != recovered_address {
+            bail!(
+                "recovered a key for 0x{} rather than the shares' account 0x{}, so a share is \
+                 wrong or corrupted",
+                recovered_address,
+                address
+            );
+        }
+    }
+    println!("Standard account address: 0x{}", recovered_address);
+    println!(
+        "Private key:              0x{}",
+        hex::encode(private_key_bytes)
+    );
     Ok(())
 }
+
+/// Parses arguments, then either runs the requested subcommand or a single search.
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Run {
+            jobs,
+            concurrent,
+            threads,
+            session,
+        }) => run_jobs(&jobs, concurrent.then_some(threads), session.as_deref()),
+        #[cfg(feature = "wizard")]
+        Some(Command::Wizard) => wizard::run(),
+        Some(Command::Recover {
+            shares,
+            scheme_byte,
+        }) => recover(&shares, scheme_byte),
+        Some(Command::DeriveBatch {
+            input,
+            multisig_nonces,
+            scheme_byte,
+            multisig_domain,
+            address_format,
+        }) => derive_batch::run(
+            &input,
+            multisig_nonces,
+            scheme_byte,
+            &multisig_domain,
+            address_format,
+        ),
+        Some(Command::Bench {
+            threads,
+            seconds,
+            baseline,
+            save_baseline,
+            max_regression,
+        }) => bench::run(
+            threads,
+            Duration::from_secs(seconds),
+            baseline.as_deref(),
+            save_baseline.as_deref(),
+            max_regression,
+        ),
+        Some(Command::Soak { hours, threads }) => soak::run(hours, threads),
+        Some(Command::Analyze {
+            addresses,
+            dictionary_lang,
+            wordlist,
+        }) => analyze::run(
+            &addresses,
+            &wordlists::load(&dictionary_lang, wordlist.as_deref())?,
+        ),
+        Some(Command::Estimate {
+            patterns,
+            multisig,
+            count,
+            threads,
+            cost_per_hour,
+        }) => estimate::run(&patterns, multisig, count, threads, cost_per_hour),
+        Some(Command::MultiEd25519 {
+            owners,
+            threshold,
+            subset_size,
+            prefix,
+            suffix,
+            threads,
+        }) => multi_ed25519::run(&owners, threshold, subset_size, prefix, suffix, threads),
+        Some(Command::Derivable {
+            function,
+            key_prefix,
+            salt_bytes,
+            first_salt,
+            salt_count,
+            prefix,
+            suffix,
+            threads,
+        }) => derivable::run(
+            &function,
+            &key_prefix,
+            salt_bytes,
+            first_salt,
+            salt_count,
+            prefix,
+            suffix,
+            threads,
+        ),
+        #[cfg(feature = "distributed")]
+        Some(Command::Serve {
+            listen,
+            token,
+            search,
+        }) => distributed::serve(&listen, &token, &search),
+        #[cfg(feature = "distributed")]
+        Some(Command::Worker {
+            connect,
+            token,
+            threads,
+            local,
+        }) => distributed::work(&connect, &token, threads, &local),
+        Some(Command::Compact { path }) => {
+            let (n_lines, n_addresses) = dedupe::compact(&path)?;
+            println!(
+                "Compacted {} lines to {} unique addresses",
+                n_lines, n_addresses
+            );
+            Ok(())
+        }
+        Some(Command::Doctor) => doctor::run(),
+        Some(Command::Modes) => {
+            modes::run();
+            Ok(())
+        }
+        Some(Command::VerifyAuditLog { path }) => {
+            match audit::verify(&path)? {
+                Some(hash) => println!("Audit log hash chain intact, ending at {}", hash),
+                None => println!("Audit log is empty"),
+            }
+            Ok(())
+        }
+        None if cli.search.patterns.is_some() => search_patterns(cli.search),
+        None => search(&validate_args(cli.search)?).map(|_| ()),
+    }
+}
@@ -0,0 +1,219 @@
+//! Run manifest recording the provenance of a run's results: the exact configuration, the binary
+//! that ran it, the hardware it ran on, and the versions of the file and payload formats it wrote,
+//! so results handed between teams can be traced back to how they were produced.
+//!
+//! The manifest is written as JSON when a run starts, then rewritten with the run's outcome when it
+//! finishes.
+
+use crate::{json_escape, CliArgs, SearchSummary, SEQUENCE_NUMBER_MULTISIG};
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Options whose values are secrets, replaced in the recorded command line.
+const SECRET_OPTIONS: [&str; 2] = ["--commit-key", "--token"];
+
+/// Placeholder recorded in place of a secret option's value.
+const REDACTED: &str = "<redacted>";
+
+/// Version of the manifest format itself.
+const MANIFEST_VERSION: u32 = 1;
+
+/// Versions of the formats a run may write, bumped whenever a format changes incompatibly.
+const SCHEMA_VERSIONS: [(&str, u32); 6] = [
+    ("attempts_file", 1),
+    ("audit_log", 1),
+    ("dedupe_db", 1),
+    ("output_file", 1),
+    ("output_json", 1),
+    ("mqtt", 2),
+];
+
+/// Manifest of a run, written to a file.
+pub struct Manifest {
+    path: PathBuf,
+    /// Fields known from the start of the run, as JSON object members
+    fields: String,
+}
+
+impl Manifest {
+    /// Write a manifest for a run about to start.
+    pub fn create(path: &Path, args: &CliArgs, run_id: &str, label: &str) -> Result<Self> {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let fields = format!(
+            "\"manifest_version\":{},\"run\":\"{}\",\"label\":\"{}\",\"started_at\":{},\
+             \"binary\":{},\"backend\":{},\"configuration\":{},\"schemas\":{}",
+            MANIFEST_VERSION,
+            run_id,
+            json_escape(label),
+            started_at,
+            binary(),
+            backend(args.threads),
+            configuration(args),
+            json_object(
+                SCHEMA_VERSIONS
+                    .iter()
+                    .map(|(name, version)| (*name, version))
+            )
+        );
+        let manifest = Self {
+            path: path.to_path_buf(),
+            fields,
+        };
+        manifest.write("\"status\":\"running\"")?;
+        Ok(manifest)
+    }
+
+    /// Rewrite the manifest with the outcome of the finished run.
+    pub fn finish(&self, summary: &SearchSummary) -> Result<()> {
+        self.write(&format!(
+            "\"status\":\"finished\",\"found\":{},\"attempts\":{},\"elapsed_ms\":{}",
+            summary.found,
+            summary.attempts,
+            summary.elapsed.as_millis()
+        ))
+    }
+
+    /// Write the manifest with some extra fields describing the run's progress.
+    fn write(&self, status: &str) -> Result<()> {
+        fs::write(&self.path, format!("{{{},{}}}\n", self.fields, status))
+            .with_context(|| format!("writing manifest {}", self.path.display()))
+    }
+}
+
+/// Version and build of the running binary.
+fn binary() -> String {
+    format!(
+        "{{\"name\":\"{}\",\"version\":\"{}\",\"target\":\"{}-{}\",\"debug\":{},\"features\":[{}]}}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        cfg!(debug_assertions),
+        json_strings(compiled_features().into_iter())
+    )
+}
+
+/// Optional Cargo features compiled into this binary.
+pub fn compiled_features() -> Vec<&'static str> {
+    [
+        ("distributed", cfg!(feature = "distributed")),
+        ("notifications", cfg!(feature = "notifications")),
+        ("wizard", cfg!(feature = "wizard")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| name)
+    .collect()
+}
+
+/// Hardware the run searches on.
+fn backend(threads: usize) -> String {
+    format!(
+        "{{\"kind\":\"cpu\",\"threads\":{},\"cpu_features\":[{}]}}",
+        threads,
+        json_strings(cpu_features().into_iter())
+    )
+}
+
+/// Detected CPU features relevant to hashing and key generation throughput.
+pub fn cpu_features() -> Vec<&'static str> {
+    let mut features = vec![];
+    #[cfg(target_arch = "x86_64")]
+    {
+        macro_rules! detect {
+            ($($feature:tt),*) => {
+                $(if std::arch::is_x86_feature_detected!($feature) {
+                    features.push($feature);
+                })*
+            };
+        }
+        detect!("sse4.1", "avx", "avx2", "avx512f", "bmi2", "adx", "sha");
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        macro_rules! detect {
+            ($($feature:tt),*) => {
+                $(if std::arch::is_aarch64_feature_detected!($feature) {
+                    features.push($feature);
+                })*
+            };
+        }
+        detect!("neon", "sha2", "sha3");
+    }
+    features
+}
+
+/// Exact configuration: the command line as given, with secrets redacted, and the search settings
+/// it resolved to after environment variables and defaults were applied.
+fn configuration(args: &CliArgs) -> String {
+    let optional = |value: &Option<String>| match value {
+        Some(value) => format!("\"{}\"", json_escape(value)),
+        None => "null".to_string(),
+    };
+    format!(
+        "{{\"command_line\":[{}],\"prefix\":{},\"suffix\":{},\"multisig\":{},\
+         \"creator_prefix\":{},\"creator_suffix\":{},\"count\":{},\"threads\":{},\
+         \"commitments\":{},\"scheme_byte\":{},\"multisig_domain\":\"{}\",\"creator_nonce\":{}}}",
+        json_strings(redacted_command_line().iter()),
+        optional(&args.prefix),
+        optional(&args.suffix),
+        args.multisig,
+        optional(&args.creator_prefix),
+        optional(&args.creator_suffix),
+        args.count,
+        args.threads,
+        args.commit_key.is_some(),
+        args.scheme_byte,
+        json_escape(&args.multisig_domain),
+        SEQUENCE_NUMBER_MULTISIG
+    )
+}
+
+/// Command line as given, converted lossily if it isn't valid Unicode, with the values of
+/// [`SECRET_OPTIONS`] replaced, whether given as `--option value` or `--option=value`.
+fn redacted_command_line() -> Vec<String> {
+    let mut redact_next = false;
+    std::env::args_os()
+        .map(|arg| {
+            let arg = arg.to_string_lossy().into_owned();
+            if std::mem::take(&mut redact_next) {
+                return REDACTED.to_string();
+            }
+            match arg.split_once('=') {
+                Some((name, _)) if SECRET_OPTIONS.contains(&name) => {
+                    format!("{}={}", name, REDACTED)
+                }
+                _ => {
+                    redact_next = SECRET_OPTIONS.contains(&arg.as_str());
+                    arg
+                }
+            }
+        })
+        .collect()
+}
+
+/// Format strings as the members of a JSON array.
+fn json_strings<S: AsRef<str>>(strings: impl Iterator<Item = S>) -> String {
+    strings
+        .map(|s| format!("\"{}\"", json_escape(s.as_ref())))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Format name and value pairs as a JSON object.
+fn json_object(members: impl Iterator<Item = (&'static str, impl std::fmt::Display)>) -> String {
+    format!(
+        "{{{}}}",
+        members
+            .map(|(name, value)| format!("\"{}\":{}", name, value))
+            .collect::<Vec<_>>()
+            .join(",")
+    )
+}
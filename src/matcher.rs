@@ -0,0 +1,85 @@
+//! Ordering of the checks a candidate address must pass, so that most candidates are rejected by
+//! the first check they meet.
+//!
+//! Rather than always checking the prefix then the suffix, checks are ordered by their expected
+//! cost per rejection, lowest first, which minimizes the expected cost of rejecting a candidate.
+//! Both terms are known up front, so nothing is measured: a check fixing `n` characters rejects a
+//! random candidate with probability `1 - 16^-n`, and prefix and suffix checks cost the same, each
+//! a byte comparison that almost always stops at the first byte. For example a short prefix with a
+//! long suffix is checked suffix first.
+
+use crate::{matches_prefix, matches_suffix, PatternBytes};
+
+/// Constraint on a candidate address.
+enum Check {
+    Prefix(PatternBytes),
+    Suffix(PatternBytes),
+}
+
+impl Check {
+    /// Return `true` if search bytes satisfy this check.
+    fn passes(&self, search_bytes: &[u8]) -> bool {
+        match self {
+            Self::Prefix(prefix) => matches_prefix(search_bytes, prefix),
+            Self::Suffix(suffix) => matches_suffix(search_bytes, suffix),
+        }
+    }
+
+    /// Probability that this check rejects a random candidate.
+    fn rejection_rate(&self) -> f64 {
+        let (Self::Prefix((bytes, nibble)) | Self::Suffix((bytes, nibble))) = self;
+        let n_characters = 2 * bytes.len() + usize::from(nibble.is_some());
+        1.0 - 16f64.powi(-(n_characters as i32))
+    }
+
+    /// Cost of running this check, relative to other checks.
+    fn cost(&self) -> f64 {
+        match self {
+            Self::Prefix(_) | Self::Suffix(_) => 1.0,
+        }
+    }
+}
+
+/// Matches candidate addresses against a prefix and suffix, in the order that rejects candidates
+/// most cheaply.
+pub struct Matcher {
+    checks: Vec<Check>,
+}
+
+impl Matcher {
+    /// Match against the given prefix and suffix bytes, where specified.
+    pub fn new(prefix: Option<PatternBytes>, suffix: Option<PatternBytes>) -> Self {
+        let mut checks = [prefix.map(Check::Prefix), suffix.map(Check::Suffix)]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        checks.sort_by(|a, b| {
+            (a.cost() / a.rejection_rate()).total_cmp(&(b.cost() / b.rejection_rate()))
+        });
+        Self { checks }
+    }
+
+    /// Return `true` if search bytes satisfy every check.
+    pub fn is_match(&self, search_bytes: &[u8]) -> bool {
+        self.checks.iter().all(|check| check.passes(search_bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_the_most_selective_pattern_first() {
+        let matcher = Matcher::new(Some((vec![0xab], None)), Some((vec![0xcd, 0xef], Some(1))));
+        assert!(matches!(
+            matcher.checks[..],
+            [Check::Suffix(_), Check::Prefix(_)]
+        ));
+        let matcher = Matcher::new(Some((vec![0xab, 0xcd], None)), Some((vec![], Some(1))));
+        assert!(matches!(
+            matcher.checks[..],
+            [Check::Prefix(_), Check::Suffix(_)]
+        ));
+    }
+}
@@ -0,0 +1,67 @@
+//! Registry of the address derivations this build can search, so users can discover what their
+//! binary can actually do.
+
+/// A supported combination of chain, signature scheme, derivation, and backend.
+struct Mode {
+    chain: &'static str,
+    scheme: &'static str,
+    derivation: &'static str,
+    backend: &'static str,
+    /// How to search in this mode
+    usage: &'static str,
+}
+
+/// Every mode this build supports. All searches run on the CPU.
+const MODES: &[Mode] = &[
+    Mode {
+        chain: "Aptos",
+        scheme: "Ed25519",
+        derivation: "standard account",
+        backend: "CPU",
+        usage: "optivanity --prefix ...",
+    },
+    Mode {
+        chain: "Aptos",
+        scheme: "Ed25519",
+        derivation: "multisig account",
+        backend: "CPU",
+        usage: "optivanity --multisig --prefix ...",
+    },
+    Mode {
+        chain: "Aptos",
+        scheme: "Ed25519",
+        derivation: "multisig account, pipelined",
+        backend: "CPU",
+        usage: "optivanity --multisig --pipeline-depth N --prefix ...",
+    },
+    Mode {
+        chain: "Aptos",
+        scheme: "MultiEd25519",
+        derivation: "owner ordering",
+        backend: "CPU",
+        usage: "optivanity multi-ed25519 --owner ... --threshold N",
+    },
+    Mode {
+        chain: "Aptos",
+        scheme: "account abstraction",
+        derivation: "derivable account (AIP-113)",
+        backend: "CPU",
+        usage: "optivanity derivable --function ...",
+    },
+];
+
+/// Print a table of the modes this build supports.
+pub fn run() {
+    println!(
+        "{:<8} {:<20} {:<28} {:<8} Usage",
+        "Chain", "Scheme", "Derivation", "Backend"
+    );
+    for mode in MODES {
+        println!(
+            "{:<8} {:<20} {:<28} {:<8} {}",
+            mode.chain, mode.scheme, mode.derivation, mode.backend, mode.usage
+        );
+    }
+    println!();
+    println!("GPU backends: none in this build");
+}
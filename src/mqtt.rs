@@ -0,0 +1,413 @@
+//! Minimal MQTT 3.1.1 publisher for run telemetry, so many instances can feed one dashboard.
+//!
+//! Only what telemetry needs is implemented: a clean-session connection, QoS 0 publishes to
+//! `<topic>/progress`, and QoS 1 publishes to `<topic>/match` (or `<topic>/digest`) and
+//! `<topic>/done`. Payloads never include private keys.
+//!
+//! Matches are published from a dedicated delivery thread, which retries with backoff until the
+//! broker acknowledges each one, so a slow or flaky broker neither stalls the search nor loses
+//! matches, short of giving up on them once the run is over. A match whose acknowledgement is lost
+//! is published again, so subscribers may see it twice.
+
+use crate::warnings;
+use anyhow::{bail, Context, Result};
+use indicatif::ProgressBar;
+use std::{
+    io::{Read, Write},
+    net::{IpAddr, SocketAddr, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed},
+        mpsc::{self, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Port to connect to when the broker address doesn't specify one.
+const DEFAULT_PORT: u16 = 1883;
+
+/// Keep alive interval to request, longer than the interval between progress publishes.
+const KEEP_ALIVE_SECONDS: u16 = 60;
+
+/// Timeout for connecting to and writing to the broker, so telemetry can't stall a search.
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Payloads the delivery queue holds before further matches wait for it to drain.
+const DELIVERY_QUEUE_CAPACITY: usize = 4096;
+
+/// Delay before the first retry of a failed delivery, doubled on each further failure.
+const MIN_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest delay between retries of a failed delivery.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How long to keep retrying undelivered payloads once the run is over.
+const FINAL_DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Broker address as `host:port`, adding the default port if it has none. A bare IPv6 address is
+/// all colons, so addresses are parsed rather than searched for a colon, and IPv6 addresses come
+/// out bracketed as `[address]:port`.
+fn with_default_port(broker: &str) -> String {
+    if broker.parse::<SocketAddr>().is_ok() {
+        return broker.to_string();
+    }
+    let host = broker.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return SocketAddr::new(ip, DEFAULT_PORT).to_string();
+    }
+    match broker.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_ok() => broker.to_string(),
+        _ => format!("{}:{}", broker, DEFAULT_PORT),
+    }
+}
+
+/// Publisher connected to an MQTT broker.
+pub struct MqttPublisher {
+    broker: String,
+    topic: String,
+    stream: Option<TcpStream>,
+    /// Identifier of the last QoS 1 publish
+    packet_id: u16,
+}
+
+impl MqttPublisher {
+    /// Connect to a broker at `host[:port]`, publishing under a base topic.
+    pub fn connect(broker: &str, topic: &str) -> Result<Self> {
+        let mut publisher = Self {
+            broker: with_default_port(broker),
+            topic: topic.trim_end_matches('/').to_string(),
+            stream: None,
+            packet_id: 0,
+        };
+        publisher.stream = Some(publisher.open()?);
+        Ok(publisher)
+    }
+
+    /// Publish a payload to a subtopic of the base topic at QoS 0, reconnecting once if the
+    /// connection has dropped. Success only means the payload was handed to the operating system.
+    pub fn publish(&mut self, subtopic: &str, payload: &str) -> Result<()> {
+        let topic = format!("{}/{}", self.topic, subtopic);
+        let mut body = encode_string(&topic);
+        body.extend(payload.as_bytes());
+        self.send(&encode_packet(0x30, &body), None)
+    }
+
+    /// Publish a payload to a subtopic of the base topic at QoS 1, reconnecting once if the
+    /// connection has dropped, and only succeed once the broker acknowledges it.
+    pub fn publish_acknowledged(&mut self, subtopic: &str, payload: &str) -> Result<()> {
+        self.packet_id = self.packet_id.wrapping_add(1).max(1);
+        let topic = format!("{}/{}", self.topic, subtopic);
+        let mut body = encode_string(&topic);
+        body.extend(self.packet_id.to_be_bytes());
+        body.extend(payload.as_bytes());
+        self.send(&encode_packet(0x32, &body), Some(self.packet_id))
+    }
+
+    /// Send a packet, awaiting the acknowledgement of a packet identifier if given, over the open
+    /// connection or else a new one.
+    fn send(&mut self, packet: &[u8], packet_id: Option<u16>) -> Result<()> {
+        if let Some(stream) = &mut self.stream {
+            if send_packet(stream, packet, packet_id).is_ok() {
+                return Ok(());
+            }
+        }
+        self.stream = None;
+        let mut stream = self.open()?;
+        send_packet(&mut stream, packet, packet_id)
+            .with_context(|| format!("publishing to MQTT broker {}", self.broker))?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    /// Open a connection and complete the CONNECT/CONNACK handshake.
+    fn open(&self) -> Result<TcpStream> {
+        let address = std::net::ToSocketAddrs::to_socket_addrs(&self.broker)
+            .with_context(|| format!("resolving MQTT broker {}", self.broker))?
+            .next()
+            .with_context(|| format!("resolving MQTT broker {}", self.broker))?;
+        let mut stream = TcpStream::connect_timeout(&address, IO_TIMEOUT)
+            .with_context(|| format!("connecting to MQTT broker {}", self.broker))?;
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+        // Protocol name and level, clean session flag, keep alive, then client identifier.
+        let mut body = encode_string("MQTT");
+        body.extend([4, 0x02]);
+        body.extend(KEEP_ALIVE_SECONDS.to_be_bytes());
+        body.extend(encode_string(&format!("optivanity-{}", std::process::id())));
+        stream.write_all(&encode_packet(0x10, &body))?;
+
+        let mut connack = [0; 4];
+        stream
+            .read_exact(&mut connack)
+            .with_context(|| format!("awaiting CONNACK from MQTT broker {}", self.broker))?;
+        if connack[0] != 0x20 || connack[3] != 0 {
+            bail!(
+                "MQTT broker {} refused connection (return code {})",
+                self.broker,
+                connack[3]
+            );
+        }
+        Ok(stream)
+    }
+}
+
+impl Drop for MqttPublisher {
+    fn drop(&mut self) {
+        if let Some(stream) = &mut self.stream {
+            let _ = stream.write_all(&encode_packet(0xe0, &[]));
+        }
+    }
+}
+
+/// Queue of payloads published in order from a dedicated thread, retrying with backoff until they
+/// are delivered.
+pub struct Delivery {
+    tx: Mutex<Option<SyncSender<(&'static str, String)>>>,
+    pending: Arc<AtomicUsize>,
+    closing: Arc<AtomicBool>,
+    thread: Mutex<Option<JoinHandle<usize>>>,
+}
+
+impl Delivery {
+    /// Start delivering through a publisher, warning above a progress bar while deliveries fail.
+    pub fn spawn(mqtt: Arc<Mutex<MqttPublisher>>, bar: &ProgressBar) -> Self {
+        let (tx, rx) = mpsc::sync_channel(DELIVERY_QUEUE_CAPACITY);
+        let pending = Arc::new(AtomicUsize::new(0));
+        let closing = Arc::new(AtomicBool::new(false));
+        let (pending2, closing2, bar) = (pending.clone(), closing.clone(), bar.clone());
+        let thread = thread::spawn(move || deliver(mqtt, rx, &pending2, &closing2, &bar));
+        Self {
+            tx: Mutex::new(Some(tx)),
+            pending,
+            closing,
+            thread: Mutex::new(Some(thread)),
+        }
+    }
+
+    /// Queue a payload for a subtopic, only waiting if the queue is full. Payloads queued after
+    /// delivery has finished are dropped.
+    pub fn send(&self, subtopic: &'static str, payload: String) {
+        if let Some(tx) = self.tx.lock().unwrap().as_ref() {
+            self.pending.fetch_add(1, Relaxed);
+            // The delivery thread only exits once the queue is closed.
+            let _ = tx.send((subtopic, payload));
+        }
+    }
+
+    /// Number of payloads queued or being retried, shared for progress reporting.
+    pub fn pending(&self) -> Arc<AtomicUsize> {
+        self.pending.clone()
+    }
+
+    /// Deliver what remains, retrying for a limited time, and return how many payloads were
+    /// never delivered.
+    pub fn finish(&self) -> usize {
+        self.closing.store(true, Relaxed);
+        self.tx.lock().unwrap().take();
+        match self.thread.lock().unwrap().take() {
+            Some(thread) => thread.join().unwrap_or(0),
+            None => 0,
+        }
+    }
+}
+
+/// Publish queued payloads in batches until the queue closes, retrying each failed payload with
+/// exponential backoff, and return how many were given up on once the run was over.
+fn deliver(
+    mqtt: Arc<Mutex<MqttPublisher>>,
+    rx: Receiver<(&'static str, String)>,
+    pending: &AtomicUsize,
+    closing: &AtomicBool,
+    bar: &ProgressBar,
+) -> usize {
+    let mut deadline = None;
+    let mut n_undelivered = 0;
+    while let Ok(first) = rx.recv() {
+        let batch = std::iter::once(first)
+            .chain(rx.try_iter())
+            .collect::<Vec<_>>();
+        for (subtopic, payload) in batch {
+            let mut backoff = MIN_RETRY_BACKOFF;
+            let mut warned = false;
+            loop {
+                let result = mqtt
+                    .lock()
+                    .unwrap()
+                    .publish_acknowledged(subtopic, &payload);
+                let Err(error) = result else {
+                    break;
+                };
+                if !warned {
+                    warned = true;
+                    bar.suspend(|| {
+                        warnings::warn(
+                            "mqtt_publish_failed",
+                            format!("{:#}, retrying in the background", error),
+                        )
+                    });
+                }
+                // Once the run is over, retry only until the final deadline.
+                if closing.load(Relaxed) {
+                    let deadline =
+                        *deadline.get_or_insert_with(|| Instant::now() + FINAL_DELIVERY_TIMEOUT);
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        n_undelivered += 1;
+                        break;
+                    }
+                    backoff = backoff.min(remaining);
+                }
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+            }
+            pending.fetch_sub(1, Relaxed);
+        }
+    }
+    n_undelivered
+}
+
+/// Match payloads held back to publish together, so that a run with many matches publishes at
+/// most one digest of them per interval rather than a message per match.
+pub struct MatchDigest {
+    interval: Duration,
+    run_tags: String,
+    last_published: Option<Instant>,
+    matches: Vec<String>,
+}
+
+impl MatchDigest {
+    /// Digest matches of a run, publishing at most once per interval.
+    pub fn new(interval: Duration, run_tags: &str) -> Self {
+        Self {
+            interval,
+            run_tags: run_tags.to_string(),
+            last_published: None,
+            matches: vec![],
+        }
+    }
+
+    /// Hold a match payload for the next digest.
+    pub fn push(&mut self, payload: String) {
+        self.matches.push(payload);
+    }
+
+    /// Take the payload of a digest of the held matches, if there are any and the interval has
+    /// passed since the previous digest, or regardless of the interval if `force`d at the end of a
+    /// run.
+    pub fn take(&mut self, force: bool) -> Option<String> {
+        let due = force
+            || self
+                .last_published
+                .is_none_or(|published| published.elapsed() >= self.interval);
+        if self.matches.is_empty() || !due {
+            return None;
+        }
+        self.last_published = Some(Instant::now());
+        Some(format!(
+            "{{{},\"matches\":[{}]}}",
+            self.run_tags,
+            self.matches.drain(..).collect::<Vec<_>>().join(",")
+        ))
+    }
+}
+
+/// Write a packet, then if it has a packet identifier, read until the broker acknowledges it with a
+/// PUBACK, skipping anything else such as acknowledgements of earlier attempts.
+fn send_packet(stream: &mut TcpStream, packet: &[u8], packet_id: Option<u16>) -> Result<()> {
+    stream.write_all(packet)?;
+    let Some(packet_id) = packet_id else {
+        return Ok(());
+    };
+    loop {
+        let (header, body) = read_packet(stream)?;
+        if header == 0x40 && body == packet_id.to_be_bytes() {
+            return Ok(());
+        }
+    }
+}
+
+/// Read a packet's first header byte and body.
+fn read_packet(stream: &mut impl Read) -> Result<(u8, Vec<u8>)> {
+    let mut header = [0; 1];
+    stream.read_exact(&mut header)?;
+    let mut length = 0;
+    // The remaining length takes up to four bytes, seven bits at a time.
+    for shift in [0, 7, 14, 21] {
+        let mut byte = [0; 1];
+        stream.read_exact(&mut byte)?;
+        length |= usize::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            let mut body = vec![0; length];
+            stream.read_exact(&mut body)?;
+            return Ok((header[0], body));
+        }
+    }
+    bail!("malformed packet length from MQTT broker")
+}
+
+/// Encode a packet from its first header byte and body, with a variable-length remaining length.
+fn encode_packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut remaining = body.len();
+    loop {
+        let mut byte = (remaining % 128) as u8;
+        remaining /= 128;
+        if remaining > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if remaining == 0 {
+            break;
+        }
+    }
+    packet.extend(body);
+    packet
+}
+
+/// Encode a length-prefixed UTF-8 string.
+fn encode_string(s: &str) -> Vec<u8> {
+    let mut encoded = (s.len() as u16).to_be_bytes().to_vec();
+    encoded.extend(s.as_bytes());
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brokers_without_a_port_get_the_default_port() {
+        for (broker, expected) in [
+            ("1.2.3.4", "1.2.3.4:1883"),
+            ("1.2.3.4:99", "1.2.3.4:99"),
+            ("::1", "[::1]:1883"),
+            ("[::1]", "[::1]:1883"),
+            ("[::1]:99", "[::1]:99"),
+            ("fe80::1:2", "[fe80::1:2]:1883"),
+            ("broker.example", "broker.example:1883"),
+            ("broker.example:99", "broker.example:99"),
+        ] {
+            assert_eq!(with_default_port(broker), expected, "{}", broker);
+        }
+    }
+
+    #[test]
+    fn packets_read_back_as_encoded() {
+        for (length, n_length_bytes) in [
+            (0, 1),
+            (127, 1),
+            (128, 2),
+            (16_383, 2),
+            (16_384, 3),
+            (2_097_152, 4),
+        ] {
+            let body = vec![0xab; length];
+            let packet = encode_packet(0x32, &body);
+            assert_eq!(packet.len(), 1 + n_length_bytes + length);
+            assert_eq!(read_packet(&mut packet.as_slice()).unwrap(), (0x32, body));
+        }
+        assert!(read_packet(&mut [0x40, 0x80, 0x80, 0x80, 0x80, 0x01].as_slice()).is_err());
+    }
+}
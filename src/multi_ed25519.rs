@@ -0,0 +1,201 @@
+//! Vanity search over the owner orderings of a MultiEd25519 account.
+//!
+//! A MultiEd25519 authentication key is the SHA3-256 of the owner public keys concatenated in
+//! order, then the signature threshold, then the MultiEd25519 scheme byte. The owners and threshold
+//! are fixed, but reordering the owners changes the hash, so the orderings (and optionally the
+//! choice of which of the candidate keys become owners) form a finite search space. Which owners
+//! sign a given transaction has no bearing on the authentication key.
+
+use crate::{is_match, prefix_bytes, suffix_bytes};
+use anyhow::{bail, Context, Result};
+use sha3::{Digest, Sha3_256};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering::Relaxed},
+        Arc,
+    },
+    thread,
+};
+
+/// Scheme identifier for MultiEd25519 authentication keys in the Aptos codebase.
+const MULTI_ED25519_SCHEME: u8 = 1;
+
+/// Maximum number of owners of a MultiEd25519 account.
+const MAX_OWNERS: usize = 32;
+
+/// Length in bytes of an Ed25519 public key.
+const PUBLIC_KEY_LENGTH: usize = 32;
+
+/// Search owner orderings for a MultiEd25519 authentication key matching a vanity pattern.
+///
+/// # Arguments
+///
+/// * `owners` - Candidate owner public keys, as hex
+/// * `threshold` - Number of signatures required by the account
+/// * `subset_size` - If specified, also search over which this many of the candidates are owners
+/// * `prefix` - The vanity prefix to search against
+/// * `suffix` - The vanity suffix to search against
+/// * `threads` - Number of threads to search with
+pub fn run(
+    owners: &[String],
+    threshold: u8,
+    subset_size: Option<usize>,
+    prefix: Option<String>,
+    suffix: Option<String>,
+    threads: usize,
+) -> Result<()> {
+    let keys = owners
+        .iter()
+        .map(|owner| {
+            let bytes = hex::decode(owner.strip_prefix("0x").unwrap_or(owner))
+                .with_context(|| format!("owner public key '{}' is not hex", owner))?;
+            if bytes.len() != PUBLIC_KEY_LENGTH {
+                bail!(
+                    "owner public key '{}' is not {} bytes",
+                    owner,
+                    PUBLIC_KEY_LENGTH
+                );
+            }
+            Ok(bytes)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if keys
+        .iter()
+        .enumerate()
+        .any(|(i, key)| keys[..i].contains(key))
+    {
+        bail!("owner public keys must be distinct");
+    }
+    let n_owners = subset_size.unwrap_or(keys.len());
+    if n_owners > keys.len() || n_owners > MAX_OWNERS {
+        bail!(
+            "a MultiEd25519 account has at most {} owners, chosen from the {} given",
+            MAX_OWNERS,
+            keys.len()
+        );
+    }
+    if threshold == 0 || threshold as usize > n_owners {
+        bail!("threshold must be between 1 and the owner count");
+    }
+    for pattern in [&prefix, &suffix].into_iter().flatten() {
+        if !pattern.chars().all(|c| c.is_ascii_hexdigit()) {
+            bail!("pattern '{}' is not valid hex", pattern);
+        }
+    }
+    let prefix = prefix_bytes(prefix.map(|s| s.to_lowercase()))?;
+    let suffix = suffix_bytes(suffix.map(|s| s.to_lowercase()))?;
+
+    println!(
+        "Searching {} owner orderings for a {}-of-{} MultiEd25519 account",
+        ordering_count(keys.len(), n_owners),
+        threshold,
+        n_owners
+    );
+
+    // Each thread walks the full sequence of orderings, hashing only every `threads`-th one.
+    let found = Arc::new(AtomicBool::new(false));
+    let keys = Arc::new(keys);
+    let handles: Vec<_> = (0..threads)
+        .map(|thread_index| {
+            let (keys, found) = (keys.clone(), found.clone());
+            let (prefix, suffix) = (prefix.clone(), suffix.clone());
+            let mut preimage = vec![0; n_owners * PUBLIC_KEY_LENGTH];
+            preimage.extend([threshold, MULTI_ED25519_SCHEME]);
+            thread::spawn(move || {
+                let mut index = 0;
+                for_each_ordering(keys.len(), n_owners, |ordering| {
+                    if found.load(Relaxed) {
+                        return Some(None);
+                    }
+                    index += 1;
+                    if (index - 1) % threads != thread_index {
+                        return None;
+                    }
+                    for (owner, &key) in preimage.chunks_mut(PUBLIC_KEY_LENGTH).zip(ordering) {
+                        owner.copy_from_slice(&keys[key]);
+                    }
+                    let auth_key = Sha3_256::digest(&preimage).to_vec();
+                    (is_match(&auth_key, &prefix, &suffix) && !found.swap(true, Relaxed))
+                        .then(|| Some((ordering.to_vec(), auth_key)))
+                })
+                .flatten()
+            })
+        })
+        .collect();
+    let mut winner = None;
+    for handle in handles {
+        winner = winner.or(handle.join().unwrap());
+    }
+
+    let Some((ordering, auth_key)) = winner else {
+        bail!("no owner ordering matches the pattern");
+    };
+    println!("Authentication key:       0x{}", hex::encode(auth_key));
+    println!("Threshold:                {}", threshold);
+    println!("Owners, in order:");
+    for key in ordering {
+        println!("  0x{}", hex::encode(&keys[key]));
+    }
+    Ok(())
+}
+
+/// Number of orderings of `k` owners chosen from `n` candidates, as a string since it can exceed
+/// any integer type.
+fn ordering_count(n: usize, k: usize) -> String {
+    ((n - k + 1)..=n)
+        .map(num::BigUint::from)
+        .product::<num::BigUint>()
+        .to_string()
+}
+
+/// Visit each ordering of `k` indices chosen from `0..n`, until the visitor returns a result.
+fn for_each_ordering<T>(
+    n: usize,
+    k: usize,
+    mut visit: impl FnMut(&[usize]) -> Option<T>,
+) -> Option<T> {
+    let mut subset: Vec<usize> = (0..k).collect();
+    loop {
+        let mut ordering = subset.clone();
+        loop {
+            if let Some(result) = visit(&ordering) {
+                return Some(result);
+            }
+            if !next_permutation(&mut ordering) {
+                break;
+            }
+        }
+        if !next_combination(&mut subset, n) {
+            return None;
+        }
+    }
+}
+
+/// Advance to the next lexicographic permutation, returning `false` once wrapped around.
+fn next_permutation(items: &mut [usize]) -> bool {
+    let Some(i) = (1..items.len()).rev().find(|&i| items[i - 1] < items[i]) else {
+        items.reverse();
+        return false;
+    };
+    let j = (i..items.len())
+        .rev()
+        .find(|&j| items[j] > items[i - 1])
+        .unwrap();
+    items.swap(i - 1, j);
+    items[i..].reverse();
+    true
+}
+
+/// Advance to the next lexicographic combination of indices below `n`, returning `false` once
+/// all combinations have been visited.
+fn next_combination(combination: &mut [usize], n: usize) -> bool {
+    let k = combination.len();
+    let Some(i) = (0..k).rev().find(|&i| combination[i] < n - k + i) else {
+        return false;
+    };
+    combination[i] += 1;
+    for j in i + 1..k {
+        combination[j] = combination[j - 1] + 1;
+    }
+    true
+}
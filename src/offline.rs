@@ -0,0 +1,237 @@
+//! Assurance that an offline run never opens a network socket.
+//!
+//! Rather than trusting that no code path touches the network, it is enforced and checked at the
+//! process level, in two ways:
+//!
+//! * On Linux, on x86-64 and AArch64, a seccomp filter makes every attempt by the search's threads
+//!   to create an IPv4 or IPv6 socket fail, however briefly the socket would have been open.
+//! * The process's open file descriptors are inspected directly, so that an auditor can rely on a
+//!   nonzero exit if any network socket was open while a check ran, such as one inherited from
+//!   the parent process. Checks run periodically, so on their own they are best-effort detection:
+//!   a socket opened and closed between two checks goes unseen.
+//!
+//! Unix domain sockets, which stdout may be connected to under a service manager, are not network
+//! sockets and are allowed.
+
+use anyhow::{bail, Result};
+
+/// Fail if the process has any network socket open, naming when the check ran.
+pub fn assert_no_network_sockets(when: &str) -> Result<()> {
+    let n_sockets = count_network_sockets()?;
+    if n_sockets > 0 {
+        bail!(
+            "offline mode: {} network socket(s) open {}, refusing to continue",
+            n_sockets,
+            when
+        );
+    }
+    Ok(())
+}
+
+/// Audit architecture identifier of this build, which system call numbers are specific to.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+const AUDIT_ARCH: u32 = 0xc000_003e;
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+/// Forbid the calling thread, and every thread it spawns from then on, from creating IPv4 or IPv6
+/// sockets, with a seccomp filter failing such `socket` calls with `EACCES`.
+///
+/// Filters can't be lifted, so this is meant for a thread dedicated to an offline search, leaving
+/// the rest of the process, such as concurrent jobs that do use the network, unaffected.
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub fn forbid_network_sockets() -> Result<()> {
+    use libc::{sock_filter, BPF_ABS, BPF_JEQ, BPF_JGE, BPF_JMP, BPF_K, BPF_LD, BPF_RET, BPF_W};
+
+    // Offsets into `struct seccomp_data` of the system call number, the architecture, and the low
+    // half of the first argument, on these little-endian architectures.
+    const NR: u32 = 0;
+    const ARCH: u32 = 4;
+    const ARG0: u32 = 16;
+    // System call numbers from the x32 ABI have this bit set, and share x86-64's architecture.
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+    let load = |offset| sock_filter {
+        code: (BPF_LD | BPF_W | BPF_ABS) as u16,
+        jt: 0,
+        jf: 0,
+        k: offset,
+    };
+    let jump = |op, k, jt, jf| sock_filter {
+        code: (BPF_JMP | op | BPF_K) as u16,
+        jt,
+        jf,
+        k,
+    };
+    let ret = |k| sock_filter {
+        code: (BPF_RET | BPF_K) as u16,
+        jt: 0,
+        jf: 0,
+        k,
+    };
+    // Jumps are relative to the next instruction. Calls from other architectures or ABIs, whose
+    // numbers can't be interpreted, are all denied.
+    let filter = [
+        load(ARCH),
+        jump(BPF_JEQ, AUDIT_ARCH, 0, 7),
+        load(NR),
+        jump(BPF_JGE, X32_SYSCALL_BIT, 5, 0),
+        jump(BPF_JEQ, libc::SYS_socket as u32, 0, 3),
+        load(ARG0),
+        jump(BPF_JEQ, libc::AF_INET as u32, 2, 0),
+        jump(BPF_JEQ, libc::AF_INET6 as u32, 1, 0),
+        ret(libc::SECCOMP_RET_ALLOW),
+        ret(libc::SECCOMP_RET_ERRNO | libc::EACCES as u32),
+    ];
+    let program = libc::sock_fprog {
+        len: filter.len() as u16,
+        filter: filter.as_ptr() as *mut sock_filter,
+    };
+    // SAFETY: `program` points to `filter`, which outlives the call, and the kernel copies it.
+    unsafe {
+        // Unprivileged processes may only install filters once they can't gain privileges.
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0
+            || libc::syscall(
+                libc::SYS_seccomp,
+                libc::SECCOMP_SET_MODE_FILTER,
+                0,
+                &program as *const libc::sock_fprog,
+            ) != 0
+        {
+            bail!(
+                "offline mode: could not forbid network sockets: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Network sockets can only be forbidden on Linux, on x86-64 and AArch64, so elsewhere offline
+/// searches rely on the periodic checks for open sockets alone.
+#[cfg(not(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+pub fn forbid_network_sockets() -> Result<()> {
+    crate::warnings::warn(
+        "offline_detection_only",
+        "network sockets can't be forbidden on this platform, only detected while open when \
+         checked",
+    );
+    Ok(())
+}
+
+/// Upper bound on file descriptors to inspect, in case the descriptor limit is unlimited.
+#[cfg(unix)]
+const MAX_FILE_DESCRIPTORS: libc::rlim_t = 1 << 20;
+
+/// Count the open IPv4 and IPv6 sockets of this process.
+#[cfg(unix)]
+fn count_network_sockets() -> Result<usize> {
+    let n_sockets = match open_file_descriptors() {
+        Some(fds) => fds.into_iter().filter(|&fd| is_network_socket(fd)).count(),
+        None => (0..descriptor_limit()? as libc::c_int)
+            .filter(|&fd| is_network_socket(fd))
+            .count(),
+    };
+    Ok(n_sockets)
+}
+
+/// List this process's open file descriptors, on Linux, where `/proc/self/fd` has an entry for
+/// each, which is much faster than probing every descriptor up to the limit.
+#[cfg(target_os = "linux")]
+fn open_file_descriptors() -> Option<Vec<libc::c_int>> {
+    let entries = std::fs::read_dir("/proc/self/fd").ok()?;
+    Some(
+        entries
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+            .collect(),
+    )
+}
+
+/// Open file descriptors can't be listed elsewhere, so every descriptor up to the limit is probed.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn open_file_descriptors() -> Option<Vec<libc::c_int>> {
+    None
+}
+
+/// Highest file descriptor, plus one, that the process could have open.
+#[cfg(unix)]
+fn descriptor_limit() -> Result<libc::rlim_t> {
+    // SAFETY: `rlimit` is plain old data, and `getrlimit` only writes to the struct it is given.
+    unsafe {
+        let mut limit: libc::rlimit = std::mem::zeroed();
+        if libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) != 0 {
+            bail!("offline mode: could not read the file descriptor limit");
+        }
+        Ok(limit.rlim_cur.min(MAX_FILE_DESCRIPTORS))
+    }
+}
+
+/// Whether a file descriptor is an open IPv4 or IPv6 socket.
+#[cfg(unix)]
+fn is_network_socket(fd: libc::c_int) -> bool {
+    // SAFETY: `stat` and `sockaddr_storage` are plain old data, and `fstat` and `getsockname`
+    // only write to the structs they are given, failing harmlessly for descriptors not open.
+    let family = unsafe {
+        let mut stat: libc::stat = std::mem::zeroed();
+        if libc::fstat(fd, &mut stat) != 0 || stat.st_mode & libc::S_IFMT != libc::S_IFSOCK {
+            return false;
+        }
+        let mut address: libc::sockaddr_storage = std::mem::zeroed();
+        let mut length = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        if libc::getsockname(
+            fd,
+            &mut address as *mut _ as *mut libc::sockaddr,
+            &mut length,
+        ) != 0
+        {
+            // Err on the side of caution for sockets that can't be identified.
+            return true;
+        }
+        libc::c_int::from(address.ss_family)
+    };
+    family == libc::AF_INET || family == libc::AF_INET6
+}
+
+/// Network sockets can't be inspected portably outside of unix.
+#[cfg(not(unix))]
+fn count_network_sockets() -> Result<usize> {
+    bail!("offline mode is only supported on unix")
+}
+
+#[cfg(all(
+    test,
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod tests {
+    use super::*;
+    use std::{
+        io::ErrorKind,
+        net::{TcpStream, UdpSocket},
+        os::unix::net::UnixDatagram,
+        thread,
+    };
+
+    #[test]
+    fn forbids_network_sockets_on_the_confined_thread_and_its_children() {
+        thread::spawn(|| {
+            forbid_network_sockets().unwrap();
+            let error = UdpSocket::bind("127.0.0.1:0").unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+            let error = thread::spawn(|| TcpStream::connect("[::1]:9").unwrap_err())
+                .join()
+                .unwrap();
+            assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+            UnixDatagram::unbound().unwrap();
+        })
+        .join()
+        .unwrap();
+        // The rest of the process is unaffected.
+        UdpSocket::bind("127.0.0.1:0").unwrap();
+    }
+}
@@ -0,0 +1,66 @@
+//! Signatures over emitted results by an operator identity key, so that downstream consumers can
+//! authenticate that results came from a trusted generation service.
+//!
+//! Each match is signed with Ed25519 over the UTF-8 message
+//! `optivanity-result:<address>:<run id>:<unix timestamp>`, with the address in full `0x` form. The
+//! private key of the match is never part of what is signed.
+
+use anyhow::{bail, Context, Result};
+use ed25519_dalek::{Signer, SigningKey};
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Operator identity key to sign results with.
+pub struct OperatorKey(SigningKey);
+
+/// Operator signature over a result.
+pub struct ResultSignature {
+    /// When the result was signed, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Operator public key, as hex
+    pub public_key: String,
+    /// Signature, as hex
+    pub signature: String,
+}
+
+impl OperatorKey {
+    /// Load an operator key from a file holding a 32-byte Ed25519 private key as hex, with or
+    /// without a leading `0x`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("reading operator key {}", path.display()))?;
+        let contents = contents.trim();
+        let bytes = hex::decode(contents.strip_prefix("0x").unwrap_or(contents))
+            .with_context(|| format!("operator key {} is not hex", path.display()))?;
+        let Ok(bytes) = <[u8; ed25519_dalek::SECRET_KEY_LENGTH]>::try_from(bytes) else {
+            bail!(
+                "operator key {} is not {} bytes",
+                path.display(),
+                ed25519_dalek::SECRET_KEY_LENGTH
+            );
+        };
+        Ok(Self(SigningKey::from_bytes(&bytes)))
+    }
+
+    /// Operator public key, as hex, for consumers to verify signatures against.
+    pub fn public_key(&self) -> String {
+        hex::encode(self.0.verifying_key().as_bytes())
+    }
+
+    /// Sign a result, given its address as hex without a leading `0x` and the run it came from.
+    pub fn sign(&self, address: &str, run_id: &str) -> ResultSignature {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let message = format!("optivanity-result:0x{}:{}:{}", address, run_id, timestamp);
+        ResultSignature {
+            timestamp,
+            public_key: self.public_key(),
+            signature: hex::encode(self.0.sign(message.as_bytes()).to_bytes()),
+        }
+    }
+}
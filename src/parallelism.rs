@@ -0,0 +1,121 @@
+//! Number of threads a search can usefully run, taking container CPU limits into account.
+//!
+//! `available_parallelism` can fail outright in restricted containers, and can report every core
+//! of the host when a cgroup CPU quota allows the process far fewer, so that a thread per reported
+//! core oversubscribes the container. The quota is read directly instead, from `cpu.max` under
+//! cgroup v2 or `cpu.cfs_quota_us` and `cpu.cfs_period_us` under cgroup v1, and searches default
+//! to whichever of the two is smaller.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread::available_parallelism,
+};
+
+/// Mount point of the cgroup filesystem.
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// CPU quota of the cgroup this process runs in.
+#[derive(Clone, Copy, Debug)]
+pub struct CpuQuota {
+    /// Number of CPUs' worth of time allowed per scheduling period, possibly fractional
+    pub cpus: f64,
+    /// Version of the cgroup hierarchy the quota was read from, 1 or 2
+    pub cgroup_version: u8,
+}
+
+/// What was detected about how many threads this process can run at once.
+#[derive(Clone, Debug)]
+pub struct Parallelism {
+    /// Parallelism reported by the standard library, or why it couldn't be determined
+    pub available: Result<usize, String>,
+    /// Tightest CPU quota of this process's cgroup and its ancestors, if any
+    pub quota: Option<CpuQuota>,
+}
+
+impl Parallelism {
+    /// Detect the parallelism and CPU quota of this process.
+    pub fn detect() -> Self {
+        Self {
+            available: available_parallelism()
+                .map(|n| n.get())
+                .map_err(|error| error.to_string()),
+            quota: cgroup_quota(),
+        }
+    }
+
+    /// Number of threads to search with by default: one per CPU of the quota, rounded up, but no
+    /// more than the available parallelism, and at least one even if neither is known.
+    pub fn threads(&self) -> usize {
+        let quota = self.quota.map(|quota| quota.cpus.ceil() as usize);
+        match (self.available.as_ref().ok().copied(), quota) {
+            (Some(available), Some(quota)) => available.min(quota),
+            (available, quota) => available.or(quota).unwrap_or(1),
+        }
+        .max(1)
+    }
+}
+
+/// Default number of threads to search with, detected afresh.
+pub fn default_threads() -> usize {
+    Parallelism::detect().threads()
+}
+
+/// Read the CPU quota of this process's cgroup, checking each ancestor too since their quotas
+/// also apply, and taking the tightest.
+fn cgroup_quota() -> Option<CpuQuota> {
+    let membership = fs::read_to_string("/proc/self/cgroup").ok()?;
+    let root = Path::new(CGROUP_ROOT);
+    let mut quotas = vec![];
+    for line in membership.lines() {
+        // Each line is `hierarchy ID:controllers:path`, with no controllers for cgroup v2.
+        let mut fields = line.splitn(3, ':');
+        let (Some(_), Some(controllers), Some(path)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        if controllers.is_empty() {
+            quotas.extend(ancestors(root, path).filter_map(|dir| v2_quota(&dir)));
+        } else if controllers.split(',').any(|controller| controller == "cpu") {
+            for mount in ["cpu,cpuacct", "cpu"] {
+                quotas.extend(ancestors(&root.join(mount), path).filter_map(|dir| v1_quota(&dir)));
+            }
+        }
+    }
+    quotas.into_iter().min_by(|a, b| a.cpus.total_cmp(&b.cpus))
+}
+
+/// Directories of a cgroup and each of its ancestors, under a hierarchy's mount point. Inside a
+/// container the cgroup's own path is often not mounted, leaving only the mount point itself.
+fn ancestors<'a>(mount: &'a Path, path: &'a str) -> impl Iterator<Item = PathBuf> + 'a {
+    Path::new(path.trim_start_matches('/'))
+        .ancestors()
+        .map(move |ancestor| mount.join(ancestor))
+}
+
+/// Read a cgroup v2 quota from `cpu.max`, as `QUOTA PERIOD` in microseconds, or `max PERIOD` for
+/// no quota.
+fn v2_quota(dir: &Path) -> Option<CpuQuota> {
+    let contents = fs::read_to_string(dir.join("cpu.max")).ok()?;
+    let (quota, period) = contents.trim().split_once(' ')?;
+    Some(CpuQuota {
+        cpus: quota_cpus(quota.parse().ok()?, period.parse().ok()?)?,
+        cgroup_version: 2,
+    })
+}
+
+/// Read a cgroup v1 quota from `cpu.cfs_quota_us`, which is -1 for no quota, and
+/// `cpu.cfs_period_us`.
+fn v1_quota(dir: &Path) -> Option<CpuQuota> {
+    let read = |name| fs::read_to_string(dir.join(name)).ok()?.trim().parse().ok();
+    Some(CpuQuota {
+        cpus: quota_cpus(read("cpu.cfs_quota_us")?, read("cpu.cfs_period_us")?)?,
+        cgroup_version: 1,
+    })
+}
+
+/// Number of CPUs a quota allows, unless it is unlimited.
+fn quota_cpus(quota: i64, period: i64) -> Option<f64> {
+    (quota > 0 && period > 0).then(|| quota as f64 / period as f64)
+}
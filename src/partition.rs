@@ -0,0 +1,86 @@
+//! Partitioning of the address space between machines, so that machines searching for the same
+//! pattern can never emit the same address.
+//!
+//! Machine `INDEX` of `COUNT` only searches addresses whose prefix is followed by `INDEX` in hex,
+//! padded to the width of the largest index. Every machine's addresses are therefore disjoint from
+//! every other's, at the cost of each search being `COUNT` times harder. `COUNT` must be a power of
+//! 16, since otherwise the indices would leave some prefixes unsearched by any machine while each
+//! search is made harder still.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::str::FromStr;
+
+/// Partition assigned to this machine, parsed from `INDEX/COUNT` with indices counting from 0.
+#[derive(Clone, Copy, Debug)]
+pub struct PartitionSpec {
+    /// Index of this machine's partition
+    pub index: u64,
+    /// Total number of partitions
+    pub count: u64,
+}
+
+impl FromStr for PartitionSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (index, count) = s
+            .split_once('/')
+            .ok_or_else(|| anyhow!("expected INDEX/COUNT, for example 0/16"))?;
+        let spec = Self {
+            index: index.trim().parse().context("invalid partition index")?,
+            count: count.trim().parse().context("invalid partition count")?,
+        };
+        if spec.index >= spec.count {
+            bail!("partition index must be less than the partition count (indices start at 0)");
+        }
+        if !is_power_of_16(spec.count) {
+            bail!("partition count must be a power of 16, such as 16 or 256");
+        }
+        Ok(spec)
+    }
+}
+
+impl PartitionSpec {
+    /// Hex characters this partition's addresses continue the prefix with.
+    pub fn prefix_characters(&self) -> String {
+        // A single partition is the whole address space, which continues the prefix with nothing.
+        let width = (self.count.trailing_zeros() / 4) as usize;
+        if width == 0 {
+            return String::new();
+        }
+        format!("{:0width$x}", self.index, width = width)
+    }
+}
+
+/// Whether a number is 16 to some power, including 1.
+fn is_power_of_16(n: u64) -> bool {
+    n.is_power_of_two() && n.trailing_zeros().is_multiple_of(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefix_characters(spec: &str) -> String {
+        spec.parse::<PartitionSpec>().unwrap().prefix_characters()
+    }
+
+    #[test]
+    fn partitions_extend_the_prefix_with_their_padded_index() {
+        assert_eq!(prefix_characters("0/1"), "");
+        assert_eq!(prefix_characters("3/16"), "3");
+        assert_eq!(prefix_characters("15/16"), "f");
+        assert_eq!(prefix_characters("10/256"), "0a");
+        assert_eq!(prefix_characters(" 255 / 256 "), "ff");
+        assert_eq!(prefix_characters("4095/4096"), "fff");
+    }
+
+    #[test]
+    fn invalid_partitions_are_rejected() {
+        for spec in [
+            "", "3", "3/", "/16", "a/16", "-1/16", "16/16", "0/0", "0/2", "0/32", "0/100",
+        ] {
+            assert!(spec.parse::<PartitionSpec>().is_err(), "{}", spec);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Random number generation for private keys.
+//!
+//! By default every private key is drawn straight from the operating system. A buffered
+//! per-thread generator seeded from the operating system is faster, at the cost of stretching each
+//! seed over many keys, so it is reseeded from the operating system periodically, and in between
+//! rekeyed from its own output so that a leaked generator state can't reveal more than the last
+//! few keys generated.
+
+use anyhow::Result;
+use ed25519_dalek::SigningKey;
+use rand::{rngs::OsRng, rngs::StdRng, SeedableRng};
+use std::time::{Duration, Instant};
+
+/// Number of keys generated between forward-secure rekeyings of a buffered generator, bounding how
+/// many past keys a leaked generator state could reveal. Also how often the reseed interval is
+/// checked, so the clock isn't read for every key.
+const REKEY_INTERVAL: u64 = 1024;
+
+/// When to reseed a buffered generator from the operating system, whichever comes first.
+#[derive(Clone, Copy, Debug)]
+pub struct ReseedPolicy {
+    /// Number of keys to generate from each seed
+    pub keys: u64,
+    /// Longest time to use each seed for
+    pub interval: Duration,
+}
+
+/// Source of private keys for a single search thread.
+pub struct KeyRng {
+    buffered: Option<BufferedRng>,
+}
+
+/// Buffered generator, with the progress of its current seed.
+struct BufferedRng {
+    rng: StdRng,
+    policy: ReseedPolicy,
+    keys_since_reseed: u64,
+    reseeded_at: Instant,
+}
+
+impl KeyRng {
+    /// Generate keys from a buffered generator reseeded according to a policy, or straight from
+    /// the operating system if there is none.
+    pub fn new(policy: Option<ReseedPolicy>) -> Result<Self> {
+        let buffered = match policy {
+            Some(policy) => Some(BufferedRng {
+                rng: StdRng::from_rng(OsRng)?,
+                policy,
+                keys_since_reseed: 0,
+                reseeded_at: Instant::now(),
+            }),
+            None => None,
+        };
+        Ok(Self { buffered })
+    }
+
+    /// Generate a private key.
+    pub fn generate_key(&mut self) -> Result<SigningKey> {
+        let Some(buffered) = &mut self.buffered else {
+            return Ok(SigningKey::generate(&mut OsRng));
+        };
+        let private_key = SigningKey::generate(&mut buffered.rng);
+        buffered.keys_since_reseed += 1;
+        let rekey = buffered.keys_since_reseed % REKEY_INTERVAL == 0;
+        if buffered.keys_since_reseed >= buffered.policy.keys
+            || (rekey && buffered.reseeded_at.elapsed() >= buffered.policy.interval)
+        {
+            buffered.rng = StdRng::from_rng(OsRng)?;
+            buffered.keys_since_reseed = 0;
+            buffered.reseeded_at = Instant::now();
+        } else if rekey {
+            // Replace the state with output derived from it, which can't be inverted.
+            buffered.rng = StdRng::from_rng(&mut buffered.rng)?;
+        }
+        Ok(private_key)
+    }
+}
@@ -0,0 +1,88 @@
+//! Thinning out matches for trivial patterns, where matches arrive faster than they can be
+//! output, so the process doesn't drown its own output path.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::{str::FromStr, time::Instant};
+
+/// Fraction of matches to keep, parsed from `KEEP/EVERY`, for example `1/100`.
+#[derive(Clone, Copy, Debug)]
+pub struct SampleSpec {
+    /// Number of matches kept out of each `every`
+    pub keep: u64,
+    /// Size of each group of matches
+    pub every: u64,
+}
+
+impl FromStr for SampleSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (keep, every) = s
+            .split_once('/')
+            .ok_or_else(|| anyhow!("expected KEEP/EVERY, for example 1/100"))?;
+        let spec = Self {
+            keep: keep
+                .trim()
+                .parse()
+                .context("invalid number of matches to keep")?,
+            every: every.trim().parse().context("invalid group size")?,
+        };
+        if spec.keep == 0 || spec.keep > spec.every {
+            bail!("must keep at least 1, and at most all, of each group of matches");
+        }
+        Ok(spec)
+    }
+}
+
+/// Decides which matches to output, by sampling and a cap on results per second.
+pub struct OutputSampler {
+    sample: Option<SampleSpec>,
+    max_per_second: Option<f64>,
+    seen: u64,
+    /// Token bucket for the rate cap, holding up to one second's worth of results
+    tokens: f64,
+    last_refill: Instant,
+    /// Number of matches not kept
+    pub skipped: u64,
+}
+
+impl OutputSampler {
+    /// Keep matches according to an optional sample and an optional cap on results per second.
+    pub fn new(sample: Option<SampleSpec>, max_per_second: Option<f64>) -> Self {
+        Self {
+            sample,
+            max_per_second,
+            seen: 0,
+            tokens: max_per_second.unwrap_or_default(),
+            last_refill: Instant::now(),
+            skipped: 0,
+        }
+    }
+
+    /// Whether to keep the next match.
+    pub fn keep(&mut self) -> bool {
+        self.seen += 1;
+        let sampled = self
+            .sample
+            .is_none_or(|spec| (self.seen - 1) % spec.every < spec.keep);
+        let keep = sampled && self.max_per_second.is_none_or(|rate| self.take_token(rate));
+        if !keep {
+            self.skipped += 1;
+        }
+        keep
+    }
+
+    /// Refill the token bucket for the time since it was last refilled, then take a token if any.
+    fn take_token(&mut self, rate: f64) -> bool {
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.last_refill).as_secs_f64() * rate)
+            .min(rate.max(1.0));
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
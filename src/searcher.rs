@@ -0,0 +1,127 @@
+//! Search API for embedding: run a vanity search on a pool of worker threads and receive each
+//! match through a callback, as the binary does but without its output, sessions, or telemetry.
+
+use crate::{
+    constraints::{self, Constraint},
+    generate_key,
+    matcher::Matcher,
+    parallelism::default_threads,
+    rng::KeyRng,
+    Match, MatchBatch, MultisigPreimage, ED25519_SCHEME, MATCH_BATCH_SIZE, MATCH_CHANNEL_CAPACITY,
+    MULTISIG_DOMAIN_SEPARATOR,
+};
+use anyhow::{bail, Result};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering::Relaxed},
+        mpsc::sync_channel,
+        Arc,
+    },
+    thread,
+};
+
+/// Vanity search to run.
+#[derive(Clone, Debug, Default)]
+pub struct SearchSpec {
+    /// Hex characters the address must start with, without a leading `0x`
+    pub prefix: Option<String>,
+    /// Hex characters the address must end with
+    pub suffix: Option<String>,
+    /// Match on the address of a multisig account created by the found account, rather than the
+    /// found account's own address
+    pub multisig: bool,
+    /// Sequence number at which the found account creates the multisig account, normally 0
+    pub creator_nonce: u64,
+    /// Number of worker threads, or 0 for one per core available to this process
+    pub threads: usize,
+}
+
+/// Runs vanity searches.
+pub struct Searcher;
+
+impl Searcher {
+    /// Search for addresses matching a spec, calling `callback` with each match until it returns
+    /// `false`, and return the number of addresses generated.
+    ///
+    /// Blocks the calling thread, on which `callback` runs, for the whole search. Fails if the
+    /// spec's patterns are not hex, or can't be matched together, rather than searching forever:
+    /// if either doesn't fit in an address, they require different characters where they overlap,
+    /// or they only admit reserved framework addresses. Also fails if every worker fails.
+    pub fn run(spec: &SearchSpec, mut callback: impl FnMut(Match) -> bool) -> Result<u64> {
+        let prefix = pattern(&spec.prefix, "prefix")?;
+        let suffix = pattern(&spec.suffix, "suffix")?;
+        constraints::check(&[
+            Constraint::prefix(prefix.as_deref().unwrap_or_default()),
+            Constraint::suffix(suffix.as_deref().unwrap_or_default()),
+        ])?;
+        let n_workers = match spec.threads {
+            0 => default_threads(),
+            threads => threads,
+        };
+        let multisig_preimage = spec
+            .multisig
+            .then(|| MultisigPreimage::new(MULTISIG_DOMAIN_SEPARATOR, spec.creator_nonce));
+
+        let (match_tx, match_rx) = sync_channel(MATCH_CHANNEL_CAPACITY);
+        let counter = Arc::new(AtomicU64::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        let workers: Vec<_> = (0..n_workers)
+            .map(|_| {
+                let (prefix, suffix) = (prefix.clone(), suffix.clone());
+                let multisig_preimage = multisig_preimage.clone();
+                let matches = MatchBatch::new(match_tx.clone(), MATCH_BATCH_SIZE);
+                let (counter, stop) = (counter.clone(), stop.clone());
+                thread::spawn(move || {
+                    generate_key(
+                        prefix,
+                        suffix,
+                        Matcher::new(None, None),
+                        ED25519_SCHEME,
+                        multisig_preimage,
+                        KeyRng::new(None)?,
+                        matches,
+                        counter,
+                        stop,
+                    )
+                })
+            })
+            .collect();
+        drop(match_tx);
+
+        // The channel only closes early if every worker has failed.
+        let satisfied = 'search: {
+            for batch in &match_rx {
+                for found_match in batch {
+                    if !callback(found_match) {
+                        break 'search true;
+                    }
+                }
+            }
+            false
+        };
+
+        // Workers blocked on a full channel fail once it's dropped, which is how they stop.
+        stop.store(true, Relaxed);
+        drop(match_rx);
+        for worker in workers {
+            match worker.join() {
+                Ok(Err(error)) if !satisfied => return Err(error),
+                Err(payload) if !satisfied => std::panic::resume_unwind(payload),
+                _ => {}
+            }
+        }
+        Ok(counter.load(Relaxed))
+    }
+}
+
+/// Lowercase an optional pattern, checking that it is hex.
+fn pattern(pattern: &Option<String>, name: &str) -> Result<Option<String>> {
+    let Some(pattern) = pattern else {
+        return Ok(None);
+    };
+    let pattern = pattern.to_lowercase();
+    if !pattern.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("{} '{}' is not valid hex", name, pattern);
+    }
+    Ok(Some(pattern))
+}
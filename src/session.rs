@@ -0,0 +1,80 @@
+//! Sessions of a jobs file, so that an interrupted run resumes only the jobs still short of their
+//! count, rather than restarting all of them.
+//!
+//! A session file is a text file with one line per emitted match, the job name and the `0x`-prefixed
+//! address separated by a tab, appended to and flushed to disk as each match is emitted.
+
+use anyhow::{bail, Context, Result};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Matches already emitted in a session, by job name.
+pub struct Session {
+    emitted: HashMap<String, Vec<String>>,
+}
+
+impl Session {
+    /// Load the matches recorded in a session file, or none if it doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut emitted: HashMap<String, Vec<String>> = HashMap::new();
+        if path.exists() {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("reading session {}", path.display()))?;
+            for (i, line) in contents.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let Some((job, address)) = line.rsplit_once('\t') else {
+                    bail!("session {} line {} has no address", path.display(), i + 1);
+                };
+                emitted
+                    .entry(job.to_string())
+                    .or_default()
+                    .push(address.to_string());
+            }
+        }
+        Ok(Self { emitted })
+    }
+
+    /// Addresses already emitted by a job, as recorded.
+    pub fn emitted(&self, job: &str) -> &[String] {
+        self.emitted.get(job).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Session file open for appending a job's matches.
+pub struct SessionLog {
+    path: PathBuf,
+    file: File,
+    job: String,
+}
+
+impl SessionLog {
+    /// Open a session file for appending a job's matches, creating it if needed.
+    pub fn open(path: &Path, job: &str) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("opening session {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            job: job.to_string(),
+        })
+    }
+
+    /// Record an emitted address, as hex without a leading `0x`, flushing it to disk before
+    /// returning.
+    pub fn record(&mut self, address: &str) -> Result<()> {
+        // Write the line at once, so concurrent jobs appending to the same file don't interleave.
+        self.file
+            .write_all(format!("{}\t0x{}\n", self.job, address).as_bytes())
+            .and_then(|_| self.file.sync_data())
+            .with_context(|| format!("writing session {}", self.path.display()))
+    }
+}
@@ -0,0 +1,254 @@
+//! Shamir secret sharing of private keys over GF(256), so that a key never has to exist whole on
+//! a single disk.
+//!
+//! Each byte of the secret is the constant term of its own random polynomial of degree
+//! `threshold - 1`, and share `x` holds every polynomial evaluated at `x`, so any `threshold`
+//! shares recover the secret by Lagrange interpolation at zero while fewer reveal nothing.
+
+use anyhow::{anyhow, bail, Context, Result};
+use rand::RngCore;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Threshold and total number of shares to split a secret into, parsed from `K/N`.
+#[derive(Clone, Copy, Debug)]
+pub struct ShamirSpec {
+    /// Number of shares needed to recover the secret
+    pub threshold: u8,
+    /// Total number of shares
+    pub shares: u8,
+}
+
+impl FromStr for ShamirSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (threshold, shares) = s
+            .split_once('/')
+            .ok_or_else(|| anyhow!("expected THRESHOLD/SHARES, for example 3/5"))?;
+        let spec = Self {
+            threshold: threshold.trim().parse().context("invalid threshold")?,
+            shares: shares.trim().parse().context("invalid share count")?,
+        };
+        if spec.threshold < 2 || spec.threshold > spec.shares {
+            bail!("threshold must be at least 2 and at most the share count");
+        }
+        Ok(spec)
+    }
+}
+
+/// Multiply in GF(256), reducing by the AES polynomial `x^8 + x^4 + x^3 + x + 1`.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+/// Multiplicative inverse in GF(256), as `a^254`.
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut power = a;
+    let mut exponent = 254u8;
+    while exponent != 0 {
+        if exponent & 1 != 0 {
+            result = gf_mul(result, power);
+        }
+        power = gf_mul(power, power);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// Split a secret into shares, returned as `(x, share bytes)` for `x` in `1..=spec.shares`.
+pub fn split(secret: &[u8], spec: ShamirSpec) -> Vec<(u8, Vec<u8>)> {
+    let mut rng = rand::rngs::OsRng;
+    let mut shares: Vec<(u8, Vec<u8>)> = (1..=spec.shares).map(|x| (x, vec![])).collect();
+    let mut coefficients = vec![0; spec.threshold as usize - 1];
+    for &byte in secret {
+        rng.fill_bytes(&mut coefficients);
+        for (x, share) in shares.iter_mut() {
+            // Evaluate by Horner's method, from the highest degree coefficient down.
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0, |acc, &coefficient| gf_mul(acc, *x) ^ coefficient);
+            share.push(gf_mul(y, *x) ^ byte);
+        }
+    }
+    shares
+}
+
+/// Recover a secret from at least threshold many distinct shares, as `(x, share bytes)`.
+pub fn combine(shares: &[(u8, Vec<u8>)]) -> Result<Vec<u8>> {
+    let Some((_, first)) = shares.first() else {
+        bail!("no shares to combine");
+    };
+    for (i, (x, share)) in shares.iter().enumerate() {
+        if *x == 0 || shares[..i].iter().any(|(other, _)| other == x) {
+            bail!("share indices must be distinct and nonzero");
+        }
+        if share.len() != first.len() {
+            bail!("shares have different lengths");
+        }
+    }
+    // Lagrange basis polynomials evaluated at zero, where subtraction is XOR in GF(256).
+    let basis: Vec<u8> = shares
+        .iter()
+        .map(|(xi, _)| {
+            shares
+                .iter()
+                .filter(|(xj, _)| xj != xi)
+                .fold(1, |acc, (xj, _)| gf_mul(acc, gf_mul(*xj, gf_inv(xj ^ xi))))
+        })
+        .collect();
+    Ok((0..first.len())
+        .map(|i| {
+            shares
+                .iter()
+                .zip(&basis)
+                .fold(0, |acc, ((_, share), &l)| acc ^ gf_mul(share[i], l))
+        })
+        .collect())
+}
+
+/// Split a private key and write each share to its own new file, readable only by its owner,
+/// returning the paths. Shares go to `dirs` in turn, so that with one directory per share (for
+/// example on different drives) no single one holds enough to recover the key.
+pub fn write_shares(
+    dirs: &[PathBuf],
+    address: &str,
+    private_key: &[u8],
+    spec: ShamirSpec,
+) -> Result<Vec<PathBuf>> {
+    split(private_key, spec)
+        .into_iter()
+        .zip(dirs.iter().cycle())
+        .map(|((x, share), dir)| {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+            let path = dir.join(format!("0x{}-share-{}-of-{}.txt", address, x, spec.shares));
+            let contents = format!(
+                "# optivanity Shamir share {} of {} ({} needed to recover)\n\
+                 # Account address: 0x{}\n\
+                 {}-{}-{}\n",
+                x,
+                spec.shares,
+                spec.threshold,
+                address,
+                spec.threshold,
+                x,
+                hex::encode(share)
+            );
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            options
+                .open(&path)
+                .and_then(|mut file| file.write_all(contents.as_bytes()))
+                .with_context(|| format!("writing {}", path.display()))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Share read back from a file written by [`write_shares`].
+pub struct Share {
+    /// Number of shares needed to recover the secret
+    pub threshold: u8,
+    /// Index the share was evaluated at
+    pub x: u8,
+    /// Share bytes
+    pub bytes: Vec<u8>,
+    /// Account address from the file's header, as hex without a leading `0x`, if present
+    pub address: Option<String>,
+}
+
+/// Read a share file written by [`write_shares`].
+pub fn read_share(path: &Path) -> Result<Share> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let address = contents.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("# Account address: 0x")
+            .map(|address| address.trim().to_lowercase())
+    });
+    let line = contents
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .ok_or_else(|| anyhow!("no share in {}", path.display()))?;
+    let mut fields = line.splitn(3, '-');
+    let (Some(threshold), Some(x), Some(share)) = (fields.next(), fields.next(), fields.next())
+    else {
+        bail!("malformed share in {}", path.display());
+    };
+    Ok(Share {
+        threshold: threshold.parse().context("invalid share threshold")?,
+        x: x.parse().context("invalid share index")?,
+        bytes: hex::decode(share).context("invalid share bytes")?,
+        address,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: [u8; 32] = [
+        0x00, 0x01, 0x7f, 0x80, 0xff, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0, 0x0f, 0x55,
+        0xaa, 0xde, 0xad, 0xbe, 0xef, 0xca, 0xfe, 0xba, 0xbe, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab,
+        0xcd, 0xef,
+    ];
+
+    fn spec(threshold: u8, shares: u8) -> ShamirSpec {
+        ShamirSpec { threshold, shares }
+    }
+
+    #[test]
+    fn any_threshold_of_the_shares_recover_the_secret() {
+        let shares = split(&SECRET, spec(3, 5));
+        assert_eq!(shares.len(), 5);
+        for subset in [[0, 1, 2], [0, 2, 4], [4, 3, 1], [1, 3, 4]] {
+            let subset: Vec<_> = subset.iter().map(|&i| shares[i].clone()).collect();
+            assert_eq!(combine(&subset).unwrap(), SECRET);
+        }
+        assert_eq!(combine(&shares).unwrap(), SECRET);
+    }
+
+    #[test]
+    fn fewer_shares_than_the_threshold_do_not_recover_the_secret() {
+        let shares = split(&SECRET, spec(3, 5));
+        assert_ne!(combine(&shares[..2]).unwrap(), SECRET);
+        assert_ne!(combine(&shares[3..]).unwrap(), SECRET);
+    }
+
+    #[test]
+    fn duplicate_or_zero_indices_are_rejected() {
+        let shares = split(&SECRET, spec(2, 3));
+        assert!(combine(&[shares[0].clone(), shares[0].clone()]).is_err());
+        assert!(combine(&[(0, shares[0].1.clone()), shares[1].clone()]).is_err());
+        assert!(combine(&[]).is_err());
+    }
+
+    #[test]
+    fn specs_need_a_threshold_between_two_and_the_share_count() {
+        assert!("3/5".parse::<ShamirSpec>().is_ok());
+        assert!("1/5".parse::<ShamirSpec>().is_err());
+        assert!("6/5".parse::<ShamirSpec>().is_err());
+        assert!("3".parse::<ShamirSpec>().is_err());
+    }
+}
@@ -0,0 +1,135 @@
+//! Simulated searches, which drive the progress display, telemetry, and outputs at a chosen rate
+//! without searching, so integrations can be developed against the tool without burning CPU.
+//!
+//! Simulated workers only count attempts. Each time the attempts of all workers together pass
+//! another `rate × hit-after`, the worker that passed it emits a freshly generated key as a
+//! match, so matches arrive every `hit-after` once all workers have started. These keys are real,
+//! but their addresses don't match the pattern.
+
+use crate::{auth_key_bytes_vec, KeyRng, MatchBatch, MultisigPreimage};
+use anyhow::{bail, Context, Result};
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering::Relaxed},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often simulated workers count attempts.
+const TICK: Duration = Duration::from_millis(10);
+
+/// Rate and match interval of a simulated search, as `rate=2M,hit-after=30s`.
+#[derive(Clone, Copy, Debug)]
+pub struct Simulation {
+    /// Attempts per second across all workers
+    pub rate: f64,
+    /// Search time between matches at the full rate
+    pub hit_after: Duration,
+}
+
+impl FromStr for Simulation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (mut rate, mut hit_after) = (None, None);
+        for field in s.split(',') {
+            match field.split_once('=') {
+                Some(("rate", value)) => rate = Some(parse_rate(value)?),
+                Some(("hit-after", value)) => hit_after = Some(parse_duration(value)?),
+                _ => bail!(
+                    "expected rate=RATE,hit-after=DURATION, for example rate=2M,hit-after=30s"
+                ),
+            }
+        }
+        let (Some(rate), Some(hit_after)) = (rate, hit_after) else {
+            bail!("expected rate=RATE,hit-after=DURATION, for example rate=2M,hit-after=30s");
+        };
+        if rate < 1.0 || hit_after.is_zero() {
+            bail!("simulated rate and match interval must be positive");
+        }
+        Ok(Self { rate, hit_after })
+    }
+}
+
+/// Parse an attempt rate, optionally with a `k`, `M`, or `G` multiplier.
+fn parse_rate(s: &str) -> Result<f64> {
+    let (number, multiplier) = match s.char_indices().last() {
+        Some((i, 'k')) => (&s[..i], 1e3),
+        Some((i, 'M')) => (&s[..i], 1e6),
+        Some((i, 'G')) => (&s[..i], 1e9),
+        _ => (s, 1.0),
+    };
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("simulated rate '{}' is not a number", s))?;
+    Ok(number * multiplier)
+}
+
+/// Parse a duration in `ms`, `s`, `m`, or `h`.
+fn parse_duration(s: &str) -> Result<Duration> {
+    let (number, unit) = s
+        .find(|c: char| c.is_ascii_alphabetic())
+        .map_or((s, ""), |i| s.split_at(i));
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("simulated duration '{}' is not a number", s))?;
+    let seconds = match unit {
+        "ms" => number / 1e3,
+        "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => bail!("simulated duration '{}' needs a unit of ms, s, m, or h", s),
+    };
+    Duration::try_from_secs_f64(seconds)
+        .with_context(|| format!("simulated duration '{}' is out of range", s))
+}
+
+/// Count attempts at this worker's share of the simulated rate until the search is over, emitting
+/// a match each time the attempts of all workers pass another match interval.
+///
+/// # Arguments
+///
+/// * `simulation` - Rate and match interval of the simulated search
+/// * `n_workers` - Number of workers sharing the rate once all have started
+/// * `scheme_byte` - Scheme identifier to derive authentication keys of emitted keys with
+/// * `multisig_preimage` - Multisig preimage to derive addresses with, if simulating a multisig
+///   search
+/// * `matches` - Buffer of matches sent to main thread
+/// * `counter` - Atomic integer that keeps track of the total number of addresses generated
+/// * `stop` - Atomic flag set by the main thread once the search is over
+pub fn run_worker(
+    simulation: Simulation,
+    n_workers: usize,
+    scheme_byte: u8,
+    mut multisig_preimage: Option<MultisigPreimage>,
+    mut matches: MatchBatch,
+    counter: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+) -> Result<()> {
+    let mut rng = KeyRng::new(None)?;
+    let rate = simulation.rate / n_workers as f64;
+    let attempts_per_match = (simulation.rate * simulation.hit_after.as_secs_f64()).max(1.0) as u64;
+    let start = Instant::now();
+    let mut n_counted = 0;
+    while !stop.load(Relaxed) {
+        thread::sleep(TICK);
+        let n_due = (start.elapsed().as_secs_f64() * rate) as u64;
+        let previous = counter.fetch_add(n_due - n_counted, Relaxed);
+        let n_matches =
+            (previous + n_due - n_counted) / attempts_per_match - previous / attempts_per_match;
+        n_counted = n_due;
+        for _ in 0..n_matches {
+            let private_key = rng.generate_key()?;
+            let auth_key = auth_key_bytes_vec(&private_key, scheme_byte);
+            let multisig_address = multisig_preimage
+                .as_mut()
+                .map(|preimage| preimage.account_address(&auth_key));
+            matches.push(&private_key, auth_key, multisig_address)?;
+        }
+        matches.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,613 @@
+//! Destinations for found matches.
+//!
+//! Every match is emitted to each of a run's sinks in turn, so that any combination of them (for
+//! example printing to the console, appending keys to a file, and publishing telemetry) works
+//! without special-casing each combination.
+
+#[cfg(feature = "notifications")]
+use crate::mqtt::{Delivery, MatchDigest};
+use crate::{
+    audit::AuditLog,
+    dedupe::DedupeRecorder,
+    json_escape,
+    operator::{OperatorKey, ResultSignature},
+    sdk_snippet,
+    session::SessionLog,
+    AddressFormat, CliArgs, SEQUENCE_NUMBER_MULTISIG,
+};
+#[cfg(feature = "distributed")]
+use crate::{distributed::CoordinatorLink, warnings};
+use anyhow::{bail, Context, Result};
+use indicatif::ProgressBar;
+use rand::{rngs::OsRng, seq::SliceRandom};
+#[cfg(feature = "notifications")]
+use std::sync::{Arc, Mutex};
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+/// Match ready for output, with keys and addresses as hex without a leading `0x`.
+pub struct MatchRecord<'a> {
+    /// Address of the matched account
+    pub address: &'a str,
+    /// Authentication key, which is also the address of the creating standard account
+    pub auth_key: &'a str,
+    pub public_key: &'a str,
+    /// Private key, unless it was split into shares or redacted. Partially redacted keys only
+    /// include their first and last few characters.
+    pub private_key: Option<&'a str>,
+    /// Whether the private key was redacted
+    pub redacted: bool,
+    /// Keyed commitment to the private key, if a commitment key was given
+    pub commitment: Option<&'a str>,
+    /// Files the private key shares were written to, if it was split into shares
+    pub share_paths: &'a [PathBuf],
+    /// Operator signature over the result, if an operator key was given
+    pub signature: Option<&'a ResultSignature>,
+    /// Whether the matched account is a multisig account
+    pub multisig: bool,
+    /// Attempts made since the previous match
+    pub match_attempts: u64,
+    /// Search time since the previous match
+    pub match_elapsed: Duration,
+    /// Attempts made since the run started
+    pub attempts: u64,
+    /// Search time since the run started
+    pub elapsed: Duration,
+    /// JSON members describing the match, built once by a [`MatchJson`] for every JSON destination
+    pub json: &'a str,
+}
+
+/// Destination for found matches.
+pub trait OutputSink {
+    /// Output a match, failing the run if it can't be output.
+    fn emit(&mut self, record: &MatchRecord) -> Result<()>;
+
+    /// Complete the output once the run is over, given its total attempts and search time, for
+    /// destinations that don't output each match as it comes.
+    fn finish(&mut self, _attempts: u64, _elapsed: Duration) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Output destination selectable on the command line.
+#[derive(Clone, Debug)]
+pub enum OutputSpec {
+    /// Print each match, with its private key, to standard output
+    Console,
+    /// Print all matches, with their private keys, as a JSON array to standard output once the
+    /// run is over, instead of any plain-text output
+    Json,
+    /// Print each match, with its private key, as a line of JSON to standard output, instead of
+    /// any plain-text output
+    Ndjson,
+    /// Append each match, with its private key, as a line of JSON to a file only the current user
+    /// can read
+    File(PathBuf),
+}
+
+impl FromStr for OutputSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            None if s == "console" => Ok(Self::Console),
+            None if s == "json" => Ok(Self::Json),
+            None if s == "ndjson" => Ok(Self::Ndjson),
+            Some(("file", path)) if !path.is_empty() => Ok(Self::File(PathBuf::from(path))),
+            _ => bail!("expected console, json, ndjson, or file:PATH"),
+        }
+    }
+}
+
+impl OutputSpec {
+    /// Return `true` if this destination writes JSON to standard output, which plain-text output
+    /// would corrupt.
+    pub fn is_structured(&self) -> bool {
+        matches!(self, Self::Json | Self::Ndjson)
+    }
+}
+
+/// Prints each match for a person to read.
+pub struct ConsoleSink {
+    args: CliArgs,
+    run_id: String,
+    bar: ProgressBar,
+}
+
+impl ConsoleSink {
+    /// Print matches for a search, suspending its progress bar while printing.
+    pub fn new(args: &CliArgs, run_id: &str, bar: &ProgressBar) -> Self {
+        Self {
+            args: args.clone(),
+            run_id: run_id.to_string(),
+            bar: bar.clone(),
+        }
+    }
+}
+
+impl OutputSink for ConsoleSink {
+    fn emit(&mut self, record: &MatchRecord) -> Result<()> {
+        let args = &self.args;
+        let address = |address| args.address_format.format(address);
+        self.bar.suspend(|| {
+            // Label the creator apart from the multisig account, so neither is funded by mistake.
+            if record.multisig {
+                println!("Multisig account address: {}", address(record.address));
+                println!("Creator account address:  {}", address(record.auth_key));
+                println!("Creator auth key:         0x{}", record.auth_key);
+                println!("Creator nonce:            {}", SEQUENCE_NUMBER_MULTISIG);
+            } else {
+                println!("Standard account address: {}", address(record.auth_key));
+                println!("Authentication key:       0x{}", record.auth_key);
+            }
+            println!("Public key:               0x{}", record.public_key);
+            if let Some(label) = &args.label {
+                println!("Run:                      {} ({})", label, self.run_id);
+            }
+            if let Some(pattern) = &args.source_pattern {
+                println!("Pattern:                  {}", pattern);
+            }
+            if let Some(threshold) = args.multisig_threshold {
+                println!(
+                    "Multisig owners:          {} (creator)",
+                    address(record.auth_key)
+                );
+                for owner in &args.multisig_owners {
+                    println!("                          {}", address(owner));
+                }
+                println!(
+                    "Signatures required:      {} of {}",
+                    threshold,
+                    args.multisig_owners.len() + 1
+                );
+            }
+            match (record.private_key, record.redacted) {
+                (Some(private_key), false) => {
+                    println!("Private key:              0x{}", private_key)
+                }
+                (Some(private_key), true) => {
+                    println!("Private key (redacted):   0x{}", private_key)
+                }
+                (None, _) => {}
+            }
+            if let Some(commitment) = record.commitment {
+                println!("Private key commitment:   0x{}", commitment);
+            }
+            if let Some(signature) = record.signature {
+                println!("Operator signature:       0x{}", signature.signature);
+                println!(
+                    "Signed at:                {} (Unix time)",
+                    signature.timestamp
+                );
+            }
+            if let Some(spec) = args.shamir {
+                println!(
+                    "Private key shares:       {} needed to recover, written to",
+                    spec.threshold
+                );
+                for path in record.share_paths {
+                    println!("                          {}", path.display());
+                }
+            }
+            println!(
+                "Attempts:                 {} since previous match, {} since start",
+                record.match_attempts, record.attempts
+            );
+            println!(
+                "Search time:              {:.2?} since previous match, {:.2?} since start",
+                record.match_elapsed, record.elapsed
+            );
+            println!();
+            if let (Some(format), Some(private_key), false) =
+                (args.export, record.private_key, record.redacted)
+            {
+                println!(
+                    "{}",
+                    sdk_snippet(format, &address(record.auth_key), private_key)
+                );
+                println!();
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Builds the JSON members describing a match, so that every JSON destination describes a match
+/// the same way.
+#[derive(Clone)]
+pub struct MatchJson {
+    run_tags: String,
+    address_format: AddressFormat,
+    owners: Vec<String>,
+    threshold: Option<u64>,
+}
+
+impl MatchJson {
+    /// Describe matches of a search, tagged with its run.
+    pub fn new(args: &CliArgs, run_tags: &str) -> Self {
+        Self {
+            run_tags: run_tags.to_string(),
+            address_format: args.address_format,
+            owners: args.multisig_owners.clone(),
+            threshold: args.multisig_threshold,
+        }
+    }
+
+    /// Members describing a match, safe to publish since they never include its private key.
+    pub fn members(&self, record: &MatchRecord) -> String {
+        let address = |address| format!("\"{}\"", self.address_format.format(address));
+        let mut members = vec![
+            self.run_tags.clone(),
+            format!("\"address\":{}", address(record.address)),
+            format!("\"account_address\":{}", address(record.auth_key)),
+            format!(
+                "\"multisig_address\":{}",
+                if record.multisig {
+                    address(record.address)
+                } else {
+                    "null".to_string()
+                }
+            ),
+            format!("\"public_key\":\"0x{}\"", record.public_key),
+            format!("\"multisig\":{}", record.multisig),
+        ];
+        // Label the creator apart from the multisig account, so neither is funded by mistake.
+        if record.multisig {
+            members.push(format!(
+                "\"creator_address\":{},\"creator_auth_key\":\"0x{}\",\"creator_nonce\":{}",
+                address(record.auth_key),
+                record.auth_key,
+                SEQUENCE_NUMBER_MULTISIG
+            ));
+        }
+        if let Some(threshold) = self.threshold {
+            members.push(format!(
+                "\"owners\":[{}],\"threshold\":{}",
+                std::iter::once(record.auth_key)
+                    .chain(self.owners.iter().map(String::as_str))
+                    .map(address)
+                    .collect::<Vec<_>>()
+                    .join(","),
+                threshold
+            ));
+        }
+        members.push(format!(
+            "\"attempts\":{},\"elapsed_ms\":{},\"total_attempts\":{},\"total_elapsed_ms\":{}",
+            record.match_attempts,
+            record.match_elapsed.as_millis(),
+            record.attempts,
+            record.elapsed.as_millis()
+        ));
+        if let Some(commitment) = record.commitment {
+            members.push(format!("\"private_key_commitment\":\"0x{}\"", commitment));
+        }
+        members.extend(record.signature.map(signature_fields));
+        members.join(",")
+    }
+}
+
+/// Appends each match as a line of JSON to a file.
+pub struct FileSink {
+    path: PathBuf,
+    file: File,
+}
+
+impl FileSink {
+    /// Open a file for appending, creating it if needed so that only the current user can read it.
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options
+            .open(&path)
+            .with_context(|| format!("opening output file {}", path.display()))?;
+        Ok(Self { path, file })
+    }
+}
+
+impl OutputSink for FileSink {
+    fn emit(&mut self, record: &MatchRecord) -> Result<()> {
+        writeln!(
+            self.file,
+            "{{{},{}}}",
+            record.json,
+            private_key_members(record)
+        )
+        .and_then(|_| self.file.sync_data())
+        .with_context(|| format!("writing output file {}", self.path.display()))
+    }
+}
+
+/// Prints each match as a JSON object to standard output, for piping into tools such as `jq`:
+/// either each as a line as soon as it's found, or all as an array once the run is over.
+pub struct JsonSink {
+    lines: bool,
+    objects: Vec<String>,
+}
+
+impl JsonSink {
+    /// Print matches as newline-delimited JSON if `lines`, or else as a JSON array.
+    pub fn new(lines: bool) -> Self {
+        Self {
+            lines,
+            objects: vec![],
+        }
+    }
+}
+
+impl OutputSink for JsonSink {
+    fn emit(&mut self, record: &MatchRecord) -> Result<()> {
+        let object = format!("{{{},{}}}", record.json, private_key_members(record));
+        if self.lines {
+            println!("{}", object);
+        } else {
+            self.objects.push(object);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _attempts: u64, _elapsed: Duration) -> Result<()> {
+        if self.lines {
+            return Ok(());
+        }
+        if self.objects.is_empty() {
+            println!("[]");
+        } else {
+            println!("[\n  {}\n]", self.objects.join(",\n  "));
+        }
+        Ok(())
+    }
+}
+
+/// Appends a record of each match to a hash-chained audit log.
+pub struct AuditSink {
+    log: AuditLog,
+    run_id: String,
+    label: String,
+    pattern: String,
+}
+
+impl AuditSink {
+    /// Record matches of a pattern by a run in an audit log.
+    pub fn new(log: AuditLog, run_id: &str, label: &str, pattern: String) -> Self {
+        Self {
+            log,
+            run_id: run_id.to_string(),
+            label: label.to_string(),
+            pattern,
+        }
+    }
+}
+
+impl OutputSink for AuditSink {
+    fn emit(&mut self, record: &MatchRecord) -> Result<()> {
+        self.log.append(
+            &self.run_id,
+            &self.label,
+            &self.pattern,
+            record.address,
+            record.public_key,
+        )
+    }
+}
+
+/// Records each match in a dedupe database, so later runs skip it.
+impl OutputSink for DedupeRecorder {
+    fn emit(&mut self, record: &MatchRecord) -> Result<()> {
+        self.record(record.address)
+    }
+}
+
+/// Records each match in a jobs session, so an interrupted session resumes where it left off.
+impl OutputSink for SessionLog {
+    fn emit(&mut self, record: &MatchRecord) -> Result<()> {
+        self.record(record.address)
+    }
+}
+
+/// Publishes each match (never its private key) as MQTT telemetry, queueing it for delivery in the
+/// background rather than failing the run or waiting if the broker can't be reached.
+#[cfg(feature = "notifications")]
+pub struct MqttSink {
+    delivery: Arc<Delivery>,
+    digest: Option<Arc<Mutex<MatchDigest>>>,
+}
+
+#[cfg(feature = "notifications")]
+impl MqttSink {
+    /// Publish matches, or hold them for a digest instead, if given one.
+    pub fn new(delivery: Arc<Delivery>, digest: Option<Arc<Mutex<MatchDigest>>>) -> Self {
+        Self { delivery, digest }
+    }
+}
+
+#[cfg(feature = "notifications")]
+impl OutputSink for MqttSink {
+    fn emit(&mut self, record: &MatchRecord) -> Result<()> {
+        let payload = format!("{{{}}}", record.json);
+        let (subtopic, payload) = match &self.digest {
+            Some(digest) => {
+                let mut digest = digest.lock().unwrap();
+                digest.push(payload);
+                match digest.take(false) {
+                    Some(payload) => ("digest", payload),
+                    None => return Ok(()),
+                }
+            }
+            None => ("match", payload),
+        };
+        self.delivery.send(subtopic, payload);
+        Ok(())
+    }
+}
+
+/// Reports each match's address and public key (never its private key) to the coordinator of a
+/// distributed search, warning rather than failing the run if the coordinator can't be reached.
+#[cfg(feature = "distributed")]
+pub struct CoordinatorSink {
+    coordinator: CoordinatorLink,
+    bar: ProgressBar,
+}
+
+#[cfg(feature = "distributed")]
+impl CoordinatorSink {
+    /// Report matches for a search, warning above its progress bar.
+    pub fn new(coordinator: CoordinatorLink, bar: &ProgressBar) -> Self {
+        Self {
+            coordinator,
+            bar: bar.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "distributed")]
+impl OutputSink for CoordinatorSink {
+    fn emit(&mut self, record: &MatchRecord) -> Result<()> {
+        if let Err(error) = self
+            .coordinator
+            .report_match(record.address, record.public_key)
+        {
+            self.bar
+                .suspend(|| warnings::warn("coordinator_unreachable", format!("{:#}", error)));
+        }
+        Ok(())
+    }
+}
+
+/// Holds back every match until the run is over, then outputs them to its sinks in a random order,
+/// so that output order doesn't reveal the order or timing in which matches were generated.
+///
+/// Per-match attempts and search times would reveal the order just as well, so each match is
+/// output with the run's average attempts and search time per match, and its totals. Sinks that
+/// record what was output, such as dedupe databases and sessions, are held back too, so a match
+/// is never recorded before it is output, and operator signatures are only made on output, so
+/// their timestamps don't reveal when a match was found.
+pub struct ShuffleSink {
+    sinks: Vec<Box<dyn OutputSink>>,
+    match_json: MatchJson,
+    signer: Option<(OperatorKey, String)>,
+    held: Vec<HeldRecord>,
+}
+
+/// Owned copy of a held back match, without its effort, which is replaced on output.
+struct HeldRecord {
+    address: String,
+    auth_key: String,
+    public_key: String,
+    private_key: Option<String>,
+    redacted: bool,
+    commitment: Option<String>,
+    share_paths: Vec<PathBuf>,
+    multisig: bool,
+}
+
+impl ShuffleSink {
+    /// Shuffle matches before outputting them to the given sinks, in their order, describing them
+    /// afresh with their replaced effort, and signing them with any operator key and run ID as
+    /// they are output. Matches emitted to this sink must be unsigned.
+    pub fn new(
+        sinks: Vec<Box<dyn OutputSink>>,
+        match_json: MatchJson,
+        signer: Option<(OperatorKey, String)>,
+    ) -> Self {
+        Self {
+            sinks,
+            match_json,
+            signer,
+            held: vec![],
+        }
+    }
+}
+
+impl OutputSink for ShuffleSink {
+    fn emit(&mut self, record: &MatchRecord) -> Result<()> {
+        self.held.push(HeldRecord {
+            address: record.address.to_string(),
+            auth_key: record.auth_key.to_string(),
+            public_key: record.public_key.to_string(),
+            private_key: record.private_key.map(String::from),
+            redacted: record.redacted,
+            commitment: record.commitment.map(String::from),
+            share_paths: record.share_paths.to_vec(),
+            multisig: record.multisig,
+        });
+        Ok(())
+    }
+
+    fn finish(&mut self, attempts: u64, elapsed: Duration) -> Result<()> {
+        self.held.shuffle(&mut OsRng);
+        let n_held = self.held.len().max(1) as u32;
+        for held in &self.held {
+            let signature = self
+                .signer
+                .as_ref()
+                .map(|(key, run_id)| key.sign(&held.address, run_id));
+            let mut record = MatchRecord {
+                address: &held.address,
+                auth_key: &held.auth_key,
+                public_key: &held.public_key,
+                private_key: held.private_key.as_deref(),
+                redacted: held.redacted,
+                commitment: held.commitment.as_deref(),
+                share_paths: &held.share_paths,
+                signature: signature.as_ref(),
+                multisig: held.multisig,
+                match_attempts: attempts / u64::from(n_held),
+                match_elapsed: elapsed / n_held,
+                attempts,
+                elapsed,
+                json: "",
+            };
+            let json = self.match_json.members(&record);
+            record.json = &json;
+            for sink in self.sinks.iter_mut() {
+                sink.emit(&record)?;
+            }
+        }
+        for sink in self.sinks.iter_mut() {
+            sink.finish(attempts, elapsed)?;
+        }
+        Ok(())
+    }
+}
+
+/// JSON members for destinations that keep private keys: the private key, or `null` if it was
+/// split or redacted, along with its redacted form and share files, where present.
+fn private_key_members(record: &MatchRecord) -> String {
+    let mut members = vec![format!(
+        "\"private_key\":{}",
+        match (record.private_key, record.redacted) {
+            (Some(private_key), false) => format!("\"0x{}\"", private_key),
+            _ => "null".to_string(),
+        }
+    )];
+    if let (Some(private_key), true) = (record.private_key, record.redacted) {
+        members.push(format!("\"private_key_redacted\":\"0x{}\"", private_key));
+    }
+    if !record.share_paths.is_empty() {
+        members.push(format!(
+            "\"private_key_shares\":[{}]",
+            record
+                .share_paths
+                .iter()
+                .map(|path| format!("\"{}\"", json_escape(&path.display().to_string())))
+                .collect::<Vec<_>>()
+                .join(",")
+        ));
+    }
+    members.join(",")
+}
+
+/// JSON fields of an operator signature over a result.
+fn signature_fields(signature: &ResultSignature) -> String {
+    format!(
+        "\"signed_at\":{},\"operator_public_key\":\"0x{}\",\"operator_signature\":\"0x{}\"",
+        signature.timestamp, signature.public_key, signature.signature
+    )
+}
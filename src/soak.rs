@@ -0,0 +1,153 @@
+//! Soak testing, to validate hardware such as an overclocked rig before trusting its output.
+//!
+//! Every thread runs the same key generation and hashing path as a search, under full load, and
+//! checks each result against an independent recomputation: the public key is derived again from
+//! the private key bytes, a signature made with the private key must verify under the public key,
+//! and the authentication key and multisig address are hashed again from freshly assembled
+//! preimages. Unstable hardware shows up as a mismatch, rather than as an unusable private key.
+
+use crate::{
+    auth_key_bytes_vec, estimate::format_seconds, public_key_bytes_vec, MultisigPreimage,
+    DERIVE_OBJECT_ADDRESS_FROM_SEED_SCHEME, ED25519_SCHEME, MULTISIG_DOMAIN_SEPARATOR,
+    SEQUENCE_NUMBER_MULTISIG,
+};
+use anyhow::{bail, Result};
+use ed25519_dalek::{Signer, SigningKey, Verifier};
+use rand::rngs::OsRng;
+use sha3::{Digest, Sha3_256};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering::Relaxed},
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often to report progress.
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Run a soak test across threads for a number of hours, failing if any check ever mismatched.
+pub fn run(hours: f64, threads: usize) -> Result<()> {
+    if !hours.is_finite() || hours <= 0.0 {
+        bail!("soak duration must be a positive number of hours");
+    }
+    let duration = Duration::from_secs_f64(hours * 60.0 * 60.0);
+    println!(
+        "Soak testing across {} threads for {}...",
+        threads,
+        format_seconds(duration.as_secs_f64())
+    );
+
+    let checked = Arc::new(AtomicU64::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let (mismatch_tx, mismatch_rx) = channel();
+    let workers: Vec<_> = (0..threads)
+        .map(|_| {
+            let (checked, stop, mismatch_tx) = (checked.clone(), stop.clone(), mismatch_tx.clone());
+            thread::spawn(move || check_keys(&checked, &stop, &mismatch_tx))
+        })
+        .collect();
+    drop(mismatch_tx);
+
+    let start_time = Instant::now();
+    let mut next_report = REPORT_INTERVAL;
+    let mut n_mismatches = 0u64;
+    while start_time.elapsed() < duration {
+        let timeout = next_report
+            .min(duration)
+            .saturating_sub(start_time.elapsed());
+        match mismatch_rx.recv_timeout(timeout) {
+            Ok(mismatch) => {
+                n_mismatches += 1;
+                eprintln!("MISMATCH after {:?}: {}", start_time.elapsed(), mismatch);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => bail!("soak test workers exited unexpectedly"),
+        }
+        if start_time.elapsed() >= next_report {
+            next_report += REPORT_INTERVAL;
+            println!(
+                "{}: {} keys checked, {} mismatches",
+                format_seconds(start_time.elapsed().as_secs_f64()),
+                checked.load(Relaxed),
+                n_mismatches
+            );
+        }
+    }
+    stop.store(true, Relaxed);
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    n_mismatches += mismatch_rx.try_iter().count() as u64;
+
+    let n_checked = checked.load(Relaxed);
+    println!("Keys checked: {}", n_checked);
+    println!("Mismatches:   {}", n_mismatches);
+    if n_mismatches > 0 {
+        bail!(
+            "{} of {} keys failed verification, so this machine's output can't be trusted",
+            n_mismatches,
+            n_checked
+        );
+    }
+    println!("No mismatches found at these settings");
+    Ok(())
+}
+
+/// Generate and check keys until stopped, reporting each mismatch.
+fn check_keys(checked: &AtomicU64, stop: &AtomicBool, mismatch_tx: &Sender<String>) {
+    let mut multisig_preimage =
+        MultisigPreimage::new(MULTISIG_DOMAIN_SEPARATOR, SEQUENCE_NUMBER_MULTISIG);
+    while !stop.load(Relaxed) {
+        // Derive as a search does.
+        let private_key = SigningKey::generate(&mut OsRng);
+        let public_key = public_key_bytes_vec(&private_key);
+        let auth_key = auth_key_bytes_vec(&private_key, ED25519_SCHEME);
+        let multisig_address = multisig_preimage.account_address(&auth_key);
+
+        if let Some(failure) = verify(&private_key, &public_key, &auth_key, &multisig_address) {
+            let mismatch = format!(
+                "{} for private key 0x{}",
+                failure,
+                hex::encode(private_key.to_bytes())
+            );
+            if mismatch_tx.send(mismatch).is_err() {
+                return;
+            }
+        }
+        checked.fetch_add(1, Relaxed);
+    }
+}
+
+/// Recompute everything derived from a private key independently, describing the first mismatch.
+fn verify(
+    private_key: &SigningKey,
+    public_key: &[u8],
+    auth_key: &[u8],
+    multisig_address: &[u8],
+) -> Option<&'static str> {
+    let recomputed = SigningKey::from_bytes(&private_key.to_bytes()).verifying_key();
+    if recomputed.as_bytes() != public_key {
+        return Some("public key recomputation differs");
+    }
+    let signature = private_key.sign(auth_key);
+    if recomputed.verify(auth_key, &signature).is_err() {
+        return Some("signature does not verify under the public key");
+    }
+    if Sha3_256::digest([public_key, &[ED25519_SCHEME]].concat()).as_slice() != auth_key {
+        return Some("authentication key recomputation differs");
+    }
+    let preimage = [
+        auth_key,
+        MULTISIG_DOMAIN_SEPARATOR.as_bytes(),
+        &SEQUENCE_NUMBER_MULTISIG.to_le_bytes(),
+        &[DERIVE_OBJECT_ADDRESS_FROM_SEED_SCHEME],
+    ]
+    .concat();
+    if Sha3_256::digest(preimage).as_slice() != multisig_address {
+        return Some("multisig address recomputation differs");
+    }
+    None
+}
@@ -0,0 +1,108 @@
+//! Sampling of search progress, shared by the progress display and any other component that
+//! subscribes to the same stream of samples, such as telemetry.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering::Relaxed},
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Time constant of the smoothed rate, which therefore reflects roughly the last few seconds.
+const SMOOTHING_TIME_CONSTANT: Duration = Duration::from_secs(5);
+
+/// Search progress at a point in time.
+#[derive(Clone, Copy, Debug)]
+pub struct StatsSample {
+    /// Time since sampling started
+    #[cfg_attr(not(feature = "notifications"), allow(dead_code))]
+    pub elapsed: Duration,
+    /// Number of addresses generated
+    pub attempts: u64,
+    /// Number of matches found
+    pub found: u64,
+    /// Addresses generated per second since the previous sample, or zero for the first sample
+    pub rate: f64,
+    /// Exponentially weighted moving average of the rate, or zero for the first sample
+    pub smoothed_rate: f64,
+    /// Whether the smoothed rate has been averaged over at least its time constant, since sampling
+    /// started or smoothing was last reset
+    pub warmed_up: bool,
+}
+
+/// Samples attempt and match counters, publishing each sample to subscribers.
+///
+/// Rates are computed from monotonic timestamps of the samples themselves, rather than assuming
+/// samples are evenly spaced, so a late sample doesn't distort them.
+pub struct Stats {
+    attempts: Arc<AtomicU64>,
+    found: Arc<AtomicU64>,
+    start: Instant,
+    smoothing_start: Instant,
+    previous: Option<(Instant, u64)>,
+    smoothed_rate: f64,
+    subscribers: Vec<Sender<StatsSample>>,
+}
+
+impl Stats {
+    /// Sample the given attempt and match counters, starting now.
+    pub fn new(attempts: Arc<AtomicU64>, found: Arc<AtomicU64>) -> Self {
+        Self {
+            attempts,
+            found,
+            start: Instant::now(),
+            smoothing_start: Instant::now(),
+            previous: None,
+            smoothed_rate: 0.0,
+            subscribers: vec![],
+        }
+    }
+
+    /// Receive every subsequent sample, until this is dropped.
+    #[cfg_attr(not(feature = "notifications"), allow(dead_code))]
+    pub fn subscribe(&mut self) -> Receiver<StatsSample> {
+        let (tx, rx) = channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    /// Restart smoothing the rate from the next sample, for example once more workers have been
+    /// started, so the smoothed rate doesn't lag behind the new rate.
+    pub fn reset_smoothing(&mut self) {
+        self.smoothed_rate = 0.0;
+        self.smoothing_start = Instant::now();
+    }
+
+    /// Take a sample, and publish it to subscribers.
+    pub fn sample(&mut self) -> StatsSample {
+        let now = Instant::now();
+        let attempts = self.attempts.load(Relaxed);
+        let mut rate = 0.0;
+        if let Some((previous_time, previous_attempts)) = self.previous {
+            let interval = now.duration_since(previous_time).as_secs_f64();
+            if interval > 0.0 {
+                rate = attempts.saturating_sub(previous_attempts) as f64 / interval;
+                let weight = 1.0 - (-interval / SMOOTHING_TIME_CONSTANT.as_secs_f64()).exp();
+                self.smoothed_rate = if self.smoothed_rate == 0.0 {
+                    rate
+                } else {
+                    self.smoothed_rate + weight * (rate - self.smoothed_rate)
+                };
+            }
+        }
+        self.previous = Some((now, attempts));
+        let elapsed = now.duration_since(self.start);
+        let sample = StatsSample {
+            elapsed,
+            attempts,
+            found: self.found.load(Relaxed),
+            rate,
+            smoothed_rate: self.smoothed_rate,
+            warmed_up: now.duration_since(self.smoothing_start) >= SMOOTHING_TIME_CONSTANT,
+        };
+        self.subscribers.retain(|tx| tx.send(sample).is_ok());
+        sample
+    }
+}
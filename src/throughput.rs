@@ -0,0 +1,75 @@
+//! Detection of sustained drops in address generation throughput during long runs.
+
+use std::time::{Duration, Instant};
+
+/// How long throughput has to stay low before it is reported.
+const SUSTAINED_DROP_DURATION: Duration = Duration::from_secs(30);
+
+/// Weight given to each new sample when updating the baseline throughput.
+const BASELINE_SMOOTHING: f64 = 0.01;
+
+/// Tracks a baseline throughput, and flags when throughput stays well below it.
+///
+/// The baseline only adapts while throughput is healthy, so that a sustained drop (thermal
+/// throttling, a dead worker, or a co-tenant stealing CPU) doesn't quietly become the new normal.
+pub struct ThroughputMonitor {
+    /// Fraction of the baseline below which throughput counts as low
+    threshold: f64,
+    baseline: Option<f64>,
+    low_since: Option<Instant>,
+    alerted: bool,
+}
+
+/// Change in throughput status worth reporting.
+pub enum ThroughputAlert {
+    /// Throughput has stayed low for a sustained period
+    Dropped { rate: f64, baseline: f64 },
+    /// Throughput has recovered after a reported drop
+    Recovered { rate: f64 },
+}
+
+impl ThroughputMonitor {
+    /// Monitor for drops of more than `drop_percent` percent below the baseline.
+    pub fn new(drop_percent: u8) -> Self {
+        Self {
+            threshold: 1.0 - f64::from(drop_percent.min(100)) / 100.0,
+            baseline: None,
+            low_since: None,
+            alerted: false,
+        }
+    }
+
+    /// Whether a sustained drop is currently being reported.
+    pub fn is_low(&self) -> bool {
+        self.alerted
+    }
+
+    /// Percentage below baseline of a given rate.
+    pub fn drop_percent(&self, rate: f64) -> f64 {
+        self.baseline
+            .map_or(0.0, |baseline| (1.0 - rate / baseline) * 100.0)
+    }
+
+    /// Observe a smoothed throughput sample, returning an alert if the status changed.
+    pub fn observe(&mut self, rate: f64) -> Option<ThroughputAlert> {
+        let Some(baseline) = self.baseline else {
+            self.baseline = Some(rate);
+            return None;
+        };
+        if rate >= baseline * self.threshold {
+            self.baseline = Some(baseline + BASELINE_SMOOTHING * (rate - baseline));
+            self.low_since = None;
+            if self.alerted {
+                self.alerted = false;
+                return Some(ThroughputAlert::Recovered { rate });
+            }
+            return None;
+        }
+        let low_since = *self.low_since.get_or_insert_with(Instant::now);
+        if !self.alerted && low_since.elapsed() >= SUSTAINED_DROP_DURATION {
+            self.alerted = true;
+            return Some(ThroughputAlert::Dropped { rate, baseline });
+        }
+        None
+    }
+}
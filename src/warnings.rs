@@ -0,0 +1,46 @@
+//! Warnings about how a search was interpreted or is likely to go, such as a pattern being
+//! normalized or a search being expected to take longer than anyone would wait.
+//!
+//! Each warning carries a stable code alongside its message, so that in JSON mode a supervising
+//! process can act on warnings without parsing prose. Interactively, warnings are highlighted.
+
+use crate::json_escape;
+use clap::ValueEnum;
+use std::{
+    fmt::Display,
+    io::IsTerminal,
+    sync::atomic::{AtomicBool, Ordering::Relaxed},
+};
+
+/// Form in which to print warnings.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WarningFormat {
+    /// Human readable text, highlighted when standard error is a terminal
+    #[default]
+    Text,
+    /// One JSON object per line, with `warning` and `message` fields
+    Json,
+}
+
+/// Whether warnings are printed as JSON, for the whole process.
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Print all subsequent warnings in the given form.
+pub fn set_format(format: WarningFormat) {
+    JSON.store(format == WarningFormat::Json, Relaxed);
+}
+
+/// Print a warning to standard error, identified by a stable code such as `pattern_lowercased`.
+pub fn warn(code: &str, message: impl Display) {
+    if JSON.load(Relaxed) {
+        eprintln!(
+            "{{\"warning\":\"{}\",\"message\":\"{}\"}}",
+            code,
+            json_escape(&message.to_string())
+        );
+    } else if std::io::stderr().is_terminal() {
+        eprintln!("\x1b[1;33mWarning:\x1b[0m {}", message);
+    } else {
+        eprintln!("Warning: {}", message);
+    }
+}
@@ -0,0 +1,145 @@
+//! Interactive wizard walking a user through configuring and launching a search.
+
+use crate::{
+    constraints::{self, Constraint},
+    estimate::{expected_seconds, format_seconds, single_thread_throughput},
+    parallelism::default_threads,
+    search, validate_args, CliArgs,
+};
+use anyhow::{bail, Result};
+use clap::Parser;
+use console::{style, Key, Term};
+use num::BigInt;
+
+/// Ask what the user wants, with live difficulty feedback, then run the configured search.
+pub fn run() -> Result<()> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        bail!("the wizard needs an interactive terminal");
+    }
+
+    term.write_line("Welcome to optivanity! Let's find you a vanity address.")?;
+    term.write_line("Measuring this machine's speed...")?;
+    let threads = default_threads();
+    let rate = single_thread_throughput(false) * threads as f64;
+    term.write_line(&format!(
+        "This machine generates about {} addresses per second across {} threads.",
+        rate as u64, threads
+    ))?;
+    term.write_line("")?;
+
+    let multisig = confirm(
+        &term,
+        "Do you want a multisig account address, instead of a standard one? [y/N] ",
+        false,
+    )?;
+    term.write_line("Each hex character (0-9, a-f) you ask for makes the search 16x longer.")?;
+    let prefix = read_pattern(&term, "Address prefix (leave blank for none): ", 0, rate)?;
+    let suffix = read_pattern(
+        &term,
+        "Address suffix (leave blank for none): ",
+        prefix.len(),
+        rate,
+    )?;
+    let count = read_number(&term, "How many addresses do you want? [1] ", 1)?;
+    let max_minutes = read_number(&term, "How many minutes are you willing to wait? [60] ", 60)?;
+    term.write_line("")?;
+
+    let n_characters = constraints::n_fixed_characters(&[
+        Constraint::prefix(&prefix),
+        Constraint::suffix(&suffix),
+    ]);
+    let expected = expected_seconds(n_characters, rate) * count as f64;
+    let proceed = if expected > (max_minutes * 60) as f64 {
+        term.write_line(&format!(
+            "{} that will take about {} on average, longer than you want to wait.",
+            style("Heads up:").yellow().bold(),
+            format_seconds(expected)
+        ))?;
+        term.write_line("Try a shorter prefix or suffix to speed things up.")?;
+        confirm(&term, "Start anyway? [y/N] ", false)?
+    } else {
+        term.write_line(&format!(
+            "That should take about {} on average.",
+            format_seconds(expected)
+        ))?;
+        confirm(&term, "Start the search? [Y/n] ", true)?
+    };
+    if !proceed {
+        return Ok(());
+    }
+    term.write_line("")?;
+
+    let mut argv = vec!["optivanity".to_string(), format!("--count={}", count)];
+    if multisig {
+        argv.push("--multisig".to_string());
+    }
+    if !prefix.is_empty() {
+        argv.push(format!("--prefix={}", prefix));
+    }
+    if !suffix.is_empty() {
+        argv.push(format!("--suffix={}", suffix));
+    }
+    search(&validate_args(CliArgs::try_parse_from(argv)?)?).map(|_| ())
+}
+
+/// Read hex characters one key at a time, showing the resulting search difficulty as they're
+/// typed.
+///
+/// # Arguments
+///
+/// * `prompt` - Prompt to show before the pattern
+/// * `n_other_characters` - Number of characters already requested in other patterns
+/// * `rate` - Addresses generated per second on this machine
+fn read_pattern(term: &Term, prompt: &str, n_other_characters: usize, rate: f64) -> Result<String> {
+    let mut pattern = String::new();
+    loop {
+        let n_characters = n_other_characters + pattern.len();
+        let feedback = format!(
+            "  (1 in {} addresses, about {})",
+            BigInt::from(16).pow(n_characters as u32),
+            format_seconds(expected_seconds(n_characters, rate))
+        );
+        term.clear_line()?;
+        term.write_str(&format!("{}{}{}", prompt, pattern, style(&feedback).dim()))?;
+        term.move_cursor_left(feedback.len())?;
+        match term.read_key()? {
+            Key::Char(c) if c.is_ascii_hexdigit() => pattern.push(c.to_ascii_lowercase()),
+            Key::Backspace => {
+                pattern.pop();
+            }
+            Key::Enter => break,
+            _ => {}
+        }
+    }
+    term.clear_line()?;
+    term.write_line(&format!("{}{}", prompt, pattern))?;
+    Ok(pattern)
+}
+
+/// Ask a yes or no question.
+fn confirm(term: &Term, prompt: &str, default: bool) -> Result<bool> {
+    loop {
+        term.write_str(prompt)?;
+        match term.read_line()?.trim().to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => term.write_line("Please answer y or n.")?,
+        }
+    }
+}
+
+/// Ask for a positive number.
+fn read_number(term: &Term, prompt: &str, default: u64) -> Result<u64> {
+    loop {
+        term.write_str(prompt)?;
+        match term.read_line()?.trim() {
+            "" => return Ok(default),
+            answer => match answer.parse() {
+                Ok(number) if number > 0 => return Ok(number),
+                _ => term.write_line("Please enter a whole number greater than zero.")?,
+            },
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Wordlists for spotting words in addresses, by language, optionally merged with a user's own.
+//!
+//! Words are kept only if they can be spelled in hex, directly or with the usual digit
+//! substitutions (`o` as `0`, `i` and `l` as `1`, `s` as `5`, `t` as `7`), and are at least
+//! [`MIN_WORD_LENGTH`] characters long so that they're unlikely to appear by chance.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::{fs, path::Path};
+
+/// Shortest word worth reporting.
+pub const MIN_WORD_LENGTH: usize = 3;
+
+/// Built-in wordlist.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Language {
+    English,
+    Spanish,
+    /// Mandarin Chinese syllables and phrases in Pinyin, without tones
+    Pinyin,
+    /// Numbers considered lucky or memorable
+    Numeric,
+}
+
+impl Language {
+    /// Words of this wordlist, before filtering for hex spellings.
+    fn words(self) -> &'static [&'static str] {
+        match self {
+            Self::English => &[
+                "accede", "baffle", "decade", "efface", "facade", "coffee", "defeat", "bleed",
+                "blessed", "decaf", "ebbed", "faded", "added", "based", "beast", "bless", "cable",
+                "idea", "beef", "cafe", "code", "cool", "dead", "deaf", "babe", "bead", "face",
+                "fade", "feed", "food", "safe", "seed", "toast", "test", "boss", "abbe", "dace",
+                "ace", "add", "bad", "bed", "bee", "cab", "dab", "dad", "fab", "fad", "fed", "odd",
+            ],
+            Self::Spanish => &[
+                "adios", "besos", "cielo", "deseo", "todos", "bebe", "beso", "cabe", "cabo",
+                "cada", "cafe", "casa", "dado", "debe", "doce", "oso", "todo", "sed", "tos",
+            ],
+            Self::Pinyin => &[
+                "facai", "dafa", "bao", "bai", "bei", "cai", "cao", "dai", "dao", "die", "fei",
+                "lao", "tai", "tao", "tie",
+            ],
+            Self::Numeric => &[
+                "1314", "8888", "6666", "7777", "1337", "2024", "1234", "888", "666", "777", "520",
+                "168", "911", "420",
+            ],
+        }
+    }
+}
+
+/// Word found in addresses, with how it's spelled in hex.
+pub struct Word {
+    pub word: String,
+    pub spelling: String,
+}
+
+/// Words from the given built-in wordlists, merged with one word per line from a file if given,
+/// spelled in hex and longest first so that the longest match wins.
+pub fn load(languages: &[Language], path: Option<&Path>) -> Result<Vec<Word>> {
+    let mut words: Vec<String> = languages
+        .iter()
+        .flat_map(|language| language.words())
+        .map(|word| word.to_string())
+        .collect();
+    if let Some(path) = path {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("reading wordlist {}", path.display()))?;
+        words.extend(
+            contents
+                .lines()
+                .map(|line| line.trim().to_lowercase())
+                .filter(|line| !line.is_empty()),
+        );
+    }
+    let mut words: Vec<Word> = words
+        .into_iter()
+        .filter_map(|word| {
+            let spelling = hex_spelling(&word)?;
+            Some(Word { word, spelling })
+        })
+        .filter(|word| word.spelling.len() >= MIN_WORD_LENGTH)
+        .collect();
+    words.sort_by(|a, b| {
+        b.spelling
+            .len()
+            .cmp(&a.spelling.len())
+            .then_with(|| a.spelling.cmp(&b.spelling))
+    });
+    words.dedup_by(|a, b| a.spelling == b.spelling);
+    Ok(words)
+}
+
+/// Spell a word in lowercase hex, if it can be.
+fn hex_spelling(word: &str) -> Option<String> {
+    word.chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            c @ ('0'..='9' | 'a'..='f') => Some(c),
+            'o' => Some('0'),
+            'i' | 'l' => Some('1'),
+            's' => Some('5'),
+            't' => Some('7'),
+            _ => None,
+        })
+        .collect()
+}